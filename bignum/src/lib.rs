@@ -0,0 +1,981 @@
+extern crate challenge1;
+extern crate challenge2;
+extern crate challenge6;
+
+#[cfg(test)]
+extern crate challenge4;
+
+use challenge1::{Base64Encoder, HexDecoder};
+use challenge2::HexEncoder;
+use challenge6::Base64Decoder;
+use std::cmp::{Ordering, max};
+use std::fmt;
+use std::iter::{FromIterator, repeat};
+use std::ops::{Add, Sub, Mul, Div, Rem, Neg, Shl, Shr};
+use std::str::FromStr;
+
+// Decimal conversion works on chunks of nine digits.
+const DECIMAL_BASE: u32 = 1000000000;
+const DECIMAL_DIGITS: usize = 9;
+
+// An arbitrary-precision unsigned integer. |limbs| are 32-bit words, least
+// significant first, without leading zeros. Zero has no limbs at all.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigUint {
+  limbs: Vec<u32>
+}
+
+impl BigUint {
+  fn from_limbs(limbs: Vec<u32>) -> BigUint {
+    let mut n = BigUint { limbs: limbs };
+    n.normalize();
+    n
+  }
+
+  fn normalize(&mut self) {
+    while self.limbs.last() == Some(&0) {
+      self.limbs.pop();
+    }
+  }
+
+  pub fn zero() -> BigUint {
+    BigUint { limbs: vec!() }
+  }
+
+  pub fn one() -> BigUint {
+    BigUint::from_u64(1)
+  }
+
+  pub fn from_u64(n: u64) -> BigUint {
+    BigUint::from_limbs(vec!(n as u32, (n >> 32) as u32))
+  }
+
+  // The value if it fits into 64 bits.
+  pub fn to_u64(&self) -> Option<u64> {
+    match self.limbs.len() {
+      0 => Some(0),
+      1 => Some(self.limbs[0] as u64),
+      2 => Some(self.limbs[0] as u64 | (self.limbs[1] as u64) << 32),
+      _ => None
+    }
+  }
+
+  pub fn from_bytes_be(bytes: &[u8]) -> BigUint {
+    let mut limbs = vec!(0u32; (bytes.len() + 3) / 4);
+
+    for (i, byte) in bytes.iter().rev().enumerate() {
+      limbs[i / 4] |= (*byte as u32) << ((i % 4) * 8);
+    }
+
+    BigUint::from_limbs(limbs)
+  }
+
+  // Big-endian bytes without leading zeros. Zero is a single zero byte.
+  pub fn to_bytes_be(&self) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(self.limbs.len() * 4);
+
+    for limb in self.limbs.iter().rev() {
+      for i in (0..4).rev() {
+        bytes.push((limb >> (i * 8)) as u8);
+      }
+    }
+
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    if zeros == bytes.len() {
+      return vec!(0);
+    }
+
+    bytes[zeros..].to_vec()
+  }
+
+  // Big-endian bytes, left-padded with zeros to |len| bytes.
+  pub fn to_bytes_be_padded(&self, len: usize) -> Vec<u8> {
+    if self.is_zero() {
+      return vec!(0; len);
+    }
+
+    let bytes = self.to_bytes_be();
+    assert!(bytes.len() <= len);
+
+    let mut padded = Vec::from_iter(repeat(0).take(len - bytes.len()));
+    padded.extend(bytes);
+    padded
+  }
+
+  // Accepts an odd number of hex digits too.
+  pub fn from_hex(hex: &str) -> BigUint {
+    if hex.len() % 2 == 1 {
+      return BigUint::from_bytes_be(&format!("0{}", hex).from_hex());
+    }
+
+    BigUint::from_bytes_be(&hex.from_hex())
+  }
+
+  pub fn from_base64(base64: &str) -> BigUint {
+    BigUint::from_bytes_be(&base64.from_base64())
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.limbs.is_empty()
+  }
+
+  pub fn is_even(&self) -> bool {
+    self.limbs.first().map_or(true, |limb| limb % 2 == 0)
+  }
+
+  // The number of significant bits.
+  pub fn bits(&self) -> usize {
+    match self.limbs.last() {
+      Some(top) => self.limbs.len() * 32 - top.leading_zeros() as usize,
+      None => 0
+    }
+  }
+
+  pub fn bit(&self, index: usize) -> bool {
+    self.limbs.get(index / 32).map_or(false, |limb| (limb >> (index % 32)) & 1 == 1)
+  }
+
+  // Returns (self / divisor, self % divisor). Panics if |divisor| is zero.
+  pub fn divmod(&self, divisor: &BigUint) -> (BigUint, BigUint) {
+    assert!(!divisor.is_zero(), "division by zero");
+
+    if self < divisor {
+      return (BigUint::zero(), self.clone());
+    }
+
+    if divisor.limbs.len() == 1 {
+      let (q, r) = self.divmod_limb(divisor.limbs[0]);
+      return (q, BigUint::from_u64(r as u64));
+    }
+
+    knuth_divmod(&self.limbs, &divisor.limbs)
+  }
+
+  fn divmod_limb(&self, divisor: u32) -> (BigUint, u32) {
+    let mut q = vec!(0; self.limbs.len());
+    let mut r = 0u64;
+
+    for i in (0..self.limbs.len()).rev() {
+      let n = (r << 32) | self.limbs[i] as u64;
+      q[i] = (n / divisor as u64) as u32;
+      r = n % divisor as u64;
+    }
+
+    (BigUint::from_limbs(q), r as u32)
+  }
+
+  pub fn gcd(&self, other: &BigUint) -> BigUint {
+    let (mut a, mut b) = (self.clone(), other.clone());
+
+    while !b.is_zero() {
+      let r = &a % &b;
+      a = b;
+      b = r;
+    }
+
+    a
+  }
+
+  // The inverse of |self| modulo |modulus|, if there is one.
+  pub fn modinv(&self, modulus: &BigUint) -> Option<BigUint> {
+    let (g, x, _) = BigInt::from(self.clone()).ext_gcd(&BigInt::from(modulus.clone()));
+
+    if g != BigInt::from(BigUint::one()) {
+      return None;
+    }
+
+    Some(x.modulo(modulus))
+  }
+
+  // Montgomery exponentiation for odd moduli, square and multiply with
+  // divisions otherwise.
+  pub fn modpow(&self, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+    assert!(!modulus.is_zero(), "division by zero");
+
+    if !modulus.is_even() {
+      return Montgomery::new(modulus).pow(self, exponent);
+    }
+
+    let base = self % modulus;
+    let mut result = &BigUint::one() % modulus;
+
+    for i in (0..exponent.bits()).rev() {
+      result = &(&result * &result) % modulus;
+      if exponent.bit(i) {
+        result = &(&result * &base) % modulus;
+      }
+    }
+
+    result
+  }
+
+  // Like modpow(), but the sequence of operations doesn't depend on the bits
+  // of |exponent|, only on the size of |modulus|. Exponents longer than the
+  // modulus leak their length. The base isn't protected, |self| is reduced
+  // with the variable-time % first. Requires an odd modulus.
+  pub fn modpow_ct(&self, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+    let bits = max(modulus.bits(), exponent.bits());
+    Montgomery::new(modulus).pow_ct(self, exponent, bits)
+  }
+
+  pub fn to_str_radix10(&self) -> String {
+    if self.is_zero() {
+      return "0".to_string();
+    }
+
+    // Collect chunks of nine digits, least significant first.
+    let mut chunks = vec!();
+    let mut n = self.clone();
+    while !n.is_zero() {
+      let (q, r) = n.divmod_limb(DECIMAL_BASE);
+      chunks.push(r);
+      n = q;
+    }
+
+    let mut s = chunks.pop().unwrap().to_string();
+    for chunk in chunks.iter().rev() {
+      s.push_str(&format!("{:09}", chunk));
+    }
+
+    s
+  }
+}
+
+// Algorithm D from Knuth, TAOCP vol. 2, 4.3.1, following Hacker's Delight.
+// |v| must have at least two limbs and |u| must not be smaller than |v|.
+fn knuth_divmod(u: &[u32], v: &[u32]) -> (BigUint, BigUint) {
+  let n = v.len();
+  let m = u.len() - n;
+
+  // Normalize so that the top bit of the divisor is set.
+  let shift = v[n - 1].leading_zeros() as usize;
+  let mut vn = shl_limbs(v, shift);
+  let mut un = shl_limbs(u, shift);
+  vn.truncate(n);
+
+  let base = 1u64 << 32;
+  let mut q = vec!(0u32; m + 1);
+
+  for j in (0..m + 1).rev() {
+    // Estimate the quotient digit from the top two limbs.
+    let num = (un[j + n] as u64) << 32 | un[j + n - 1] as u64;
+    let mut qhat = num / vn[n - 1] as u64;
+    let mut rhat = num % vn[n - 1] as u64;
+
+    while qhat >= base || qhat * vn[n - 2] as u64 > (rhat << 32 | un[j + n - 2] as u64) {
+      qhat -= 1;
+      rhat += vn[n - 1] as u64;
+      if rhat >= base {
+        break;
+      }
+    }
+
+    // Multiply and subtract.
+    let mut k = 0i64;
+    for i in 0..n {
+      let p = qhat * vn[i] as u64;
+      let t = un[i + j] as i64 - k - (p & 0xffffffff) as i64;
+      un[i + j] = t as u32;
+      k = (p >> 32) as i64 - (t >> 32);
+    }
+
+    let t = un[j + n] as i64 - k;
+    un[j + n] = t as u32;
+    q[j] = qhat as u32;
+
+    // We subtracted too much, add back.
+    if t < 0 {
+      q[j] = q[j].wrapping_sub(1);
+
+      let mut carry = 0u64;
+      for i in 0..n {
+        let sum = un[i + j] as u64 + vn[i] as u64 + carry;
+        un[i + j] = sum as u32;
+        carry = sum >> 32;
+      }
+      un[j + n] = un[j + n].wrapping_add(carry as u32);
+    }
+  }
+
+  un.truncate(n);
+  let r = BigUint::from_limbs(un) >> shift;
+  (BigUint::from_limbs(q), r)
+}
+
+// Shifts |limbs| left by |shift| < 32 bits, keeping the carry limb.
+fn shl_limbs(limbs: &[u32], shift: usize) -> Vec<u32> {
+  let mut result = Vec::with_capacity(limbs.len() + 1);
+  let mut carry = 0;
+
+  for limb in limbs {
+    result.push((limb << shift) | carry);
+    carry = if shift > 0 { limb >> (32 - shift) } else { 0 };
+  }
+
+  result.push(carry);
+  result
+}
+
+impl PartialOrd for BigUint {
+  fn partial_cmp(&self, other: &BigUint) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for BigUint {
+  fn cmp(&self, other: &BigUint) -> Ordering {
+    if self.limbs.len() != other.limbs.len() {
+      return self.limbs.len().cmp(&other.limbs.len());
+    }
+
+    for (a, b) in self.limbs.iter().rev().zip(other.limbs.iter().rev()) {
+      if a != b {
+        return a.cmp(b);
+      }
+    }
+
+    Ordering::Equal
+  }
+}
+
+impl fmt::Display for BigUint {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.to_str_radix10())
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseBigIntError;
+
+impl FromStr for BigUint {
+  type Err = ParseBigIntError;
+
+  // Parses decimal numbers.
+  fn from_str(s: &str) -> Result<BigUint, ParseBigIntError> {
+    if s.is_empty() || !s.bytes().all(|byte| byte >= b'0' && byte <= b'9') {
+      return Err(ParseBigIntError);
+    }
+
+    let mut n = BigUint::zero();
+    let bytes = s.as_bytes();
+
+    // The first chunk gets the leftover digits.
+    let mut start = 0;
+    let mut end = bytes.len() % DECIMAL_DIGITS;
+    if end == 0 {
+      end = DECIMAL_DIGITS;
+    }
+
+    while start < bytes.len() {
+      let chunk = bytes[start..end].iter().fold(0, |chunk, byte| chunk * 10 + (byte - b'0') as u64);
+      let scale = 10u64.pow((end - start) as u32);
+      n = &(&n * &BigUint::from_u64(scale)) + &BigUint::from_u64(chunk);
+
+      start = end;
+      end += DECIMAL_DIGITS;
+    }
+
+    Ok(n)
+  }
+}
+
+impl HexEncoder for BigUint {
+  fn to_hex(&self) -> String {
+    self.to_bytes_be().to_hex()
+  }
+}
+
+impl Base64Encoder for BigUint {
+  fn to_base64(&self) -> String {
+    self.to_bytes_be().to_base64()
+  }
+}
+
+// Implements the owned versions of a binary operator in terms of the one
+// for references.
+macro_rules! forward_binop {
+  ($t:ty, $imp:ident, $method:ident) => {
+    impl $imp<$t> for $t {
+      type Output = $t;
+
+      fn $method(self, other: $t) -> $t {
+        (&self).$method(&other)
+      }
+    }
+
+    impl<'a> $imp<&'a $t> for $t {
+      type Output = $t;
+
+      fn $method(self, other: &$t) -> $t {
+        (&self).$method(other)
+      }
+    }
+  }
+}
+
+impl<'a, 'b> Add<&'b BigUint> for &'a BigUint {
+  type Output = BigUint;
+
+  fn add(self, other: &BigUint) -> BigUint {
+    let len = max(self.limbs.len(), other.limbs.len());
+    let mut limbs = Vec::with_capacity(len + 1);
+    let mut carry = 0u64;
+
+    for i in 0..len {
+      let a = *self.limbs.get(i).unwrap_or(&0) as u64;
+      let b = *other.limbs.get(i).unwrap_or(&0) as u64;
+      let sum = a + b + carry;
+      limbs.push(sum as u32);
+      carry = sum >> 32;
+    }
+
+    limbs.push(carry as u32);
+    BigUint::from_limbs(limbs)
+  }
+}
+
+impl<'a, 'b> Sub<&'b BigUint> for &'a BigUint {
+  type Output = BigUint;
+
+  // Panics if the result would be negative.
+  fn sub(self, other: &BigUint) -> BigUint {
+    assert!(self >= other, "subtraction underflow");
+
+    let mut limbs = Vec::with_capacity(self.limbs.len());
+    let mut borrow = 0i64;
+
+    for i in 0..self.limbs.len() {
+      let b = *other.limbs.get(i).unwrap_or(&0) as i64;
+      let diff = self.limbs[i] as i64 - b - borrow;
+      limbs.push(diff as u32);
+      borrow = if diff < 0 { 1 } else { 0 };
+    }
+
+    BigUint::from_limbs(limbs)
+  }
+}
+
+impl<'a, 'b> Mul<&'b BigUint> for &'a BigUint {
+  type Output = BigUint;
+
+  // Schoolbook multiplication.
+  fn mul(self, other: &BigUint) -> BigUint {
+    let mut limbs = vec!(0u32; self.limbs.len() + other.limbs.len());
+
+    for (i, a) in self.limbs.iter().enumerate() {
+      let mut carry = 0u64;
+
+      for (j, b) in other.limbs.iter().enumerate() {
+        let product = *a as u64 * *b as u64 + limbs[i + j] as u64 + carry;
+        limbs[i + j] = product as u32;
+        carry = product >> 32;
+      }
+
+      limbs[i + other.limbs.len()] = carry as u32;
+    }
+
+    BigUint::from_limbs(limbs)
+  }
+}
+
+impl<'a, 'b> Div<&'b BigUint> for &'a BigUint {
+  type Output = BigUint;
+
+  fn div(self, other: &BigUint) -> BigUint {
+    self.divmod(other).0
+  }
+}
+
+impl<'a, 'b> Rem<&'b BigUint> for &'a BigUint {
+  type Output = BigUint;
+
+  fn rem(self, other: &BigUint) -> BigUint {
+    self.divmod(other).1
+  }
+}
+
+forward_binop!(BigUint, Add, add);
+forward_binop!(BigUint, Sub, sub);
+forward_binop!(BigUint, Mul, mul);
+forward_binop!(BigUint, Div, div);
+forward_binop!(BigUint, Rem, rem);
+
+impl Shl<usize> for BigUint {
+  type Output = BigUint;
+
+  fn shl(self, bits: usize) -> BigUint {
+    let mut limbs = Vec::from_iter(repeat(0).take(bits / 32));
+    limbs.extend(shl_limbs(&self.limbs, bits % 32));
+    BigUint::from_limbs(limbs)
+  }
+}
+
+impl Shr<usize> for BigUint {
+  type Output = BigUint;
+
+  fn shr(self, bits: usize) -> BigUint {
+    let (words, shift) = (bits / 32, bits % 32);
+    if words >= self.limbs.len() {
+      return BigUint::zero();
+    }
+
+    let limbs = &self.limbs[words..];
+    let mut result = Vec::with_capacity(limbs.len());
+
+    for i in 0..limbs.len() {
+      let next = if shift > 0 { limbs.get(i + 1).map_or(0, |limb| limb << (32 - shift)) } else { 0 };
+      result.push((limbs[i] >> shift) | next);
+    }
+
+    BigUint::from_limbs(result)
+  }
+}
+
+// Montgomery arithmetic modulo an odd |modulus| with R = 2^(32 * len).
+// Numbers in Montgomery form always have exactly |len| limbs.
+pub struct Montgomery {
+  modulus: Vec<u32>,
+  // -modulus^-1 mod 2^32
+  inv: u32,
+  // R^2 mod modulus, to convert into Montgomery form.
+  r2: BigUint
+}
+
+impl Montgomery {
+  pub fn new(modulus: &BigUint) -> Montgomery {
+    assert!(!modulus.is_even(), "Montgomery needs an odd modulus");
+
+    // Newton's iteration doubles the number of correct bits every step.
+    let m0 = modulus.limbs[0];
+    let mut inv = 1u32;
+    for _ in 0..5 {
+      inv = inv.wrapping_mul(2u32.wrapping_sub(m0.wrapping_mul(inv)));
+    }
+
+    let len = modulus.limbs.len();
+    let r2 = &(BigUint::one() << (64 * len)) % modulus;
+    Montgomery { modulus: modulus.limbs.clone(), inv: inv.wrapping_neg(), r2: r2 }
+  }
+
+  fn len(&self) -> usize {
+    self.modulus.len()
+  }
+
+  fn pad(&self, n: &BigUint) -> Vec<u32> {
+    let mut limbs = n.limbs.clone();
+    limbs.extend(repeat(0).take(self.len() - n.limbs.len()));
+    limbs
+  }
+
+  // Not constant time, |n| is reduced with %.
+  pub fn to_montgomery(&self, n: &BigUint) -> Vec<u32> {
+    let n = n % &BigUint { limbs: self.modulus.clone() };
+    let r2 = self.pad(&self.r2);
+    self.mul(&self.pad(&n), &r2)
+  }
+
+  pub fn from_montgomery(&self, n: &[u32]) -> BigUint {
+    let one = self.pad(&BigUint::one());
+    BigUint::from_limbs(self.mul(n, &one))
+  }
+
+  // a * b * R^-1 mod modulus, with the CIOS method. The final subtraction
+  // happens in constant time, masking instead of branching.
+  pub fn mul(&self, a: &[u32], b: &[u32]) -> Vec<u32> {
+    let n = self.len();
+    let m = &self.modulus;
+    let mut t = vec!(0u32; n + 2);
+
+    for i in 0..n {
+      let mut carry = 0u64;
+      for j in 0..n {
+        let sum = t[j] as u64 + a[j] as u64 * b[i] as u64 + carry;
+        t[j] = sum as u32;
+        carry = sum >> 32;
+      }
+
+      let sum = t[n] as u64 + carry;
+      t[n] = sum as u32;
+      t[n + 1] = (sum >> 32) as u32;
+
+      // Add a multiple of the modulus so that the lowest limb is zero.
+      let q = t[0].wrapping_mul(self.inv);
+      let mut carry = (t[0] as u64 + q as u64 * m[0] as u64) >> 32;
+      for j in 1..n {
+        let sum = t[j] as u64 + q as u64 * m[j] as u64 + carry;
+        t[j - 1] = sum as u32;
+        carry = sum >> 32;
+      }
+
+      let sum = t[n] as u64 + carry;
+      t[n - 1] = sum as u32;
+      t[n] = t[n + 1] + (sum >> 32) as u32;
+    }
+
+    // Subtract the modulus if t >= modulus.
+    let mut diff = Vec::with_capacity(n);
+    let mut borrow = 0i64;
+    for j in 0..n {
+      let d = t[j] as i64 - m[j] as i64 - borrow;
+      diff.push(d as u32);
+      borrow = if d < 0 { 1 } else { 0 };
+    }
+
+    // Keep |t| only if the subtraction borrowed beyond t[n].
+    let keep = ((t[n] as i64 - borrow) < 0) as u32;
+    let mask = keep.wrapping_neg();
+    Vec::from_iter((0..n).map(|j| (t[j] & mask) | (diff[j] & !mask)))
+  }
+
+  pub fn pow(&self, base: &BigUint, exponent: &BigUint) -> BigUint {
+    let base = self.to_montgomery(base);
+    let mut result = self.to_montgomery(&BigUint::one());
+
+    for i in (0..exponent.bits()).rev() {
+      result = self.mul(&result, &result);
+      if exponent.bit(i) {
+        result = self.mul(&result, &base);
+      }
+    }
+
+    self.from_montgomery(&result)
+  }
+
+  // A Montgomery ladder over |bits| exponent bits. Every step does one
+  // multiplication and one squaring, and swaps operands with masks.
+  pub fn pow_ct(&self, base: &BigUint, exponent: &BigUint, bits: usize) -> BigUint {
+    assert!(exponent.bits() <= bits);
+
+    let mut r0 = self.to_montgomery(&BigUint::one());
+    let mut r1 = self.to_montgomery(base);
+
+    for i in (0..bits).rev() {
+      let mask = (exponent.bit(i) as u32).wrapping_neg();
+      cswap(&mut r0, &mut r1, mask);
+      r1 = self.mul(&r0, &r1);
+      r0 = self.mul(&r0, &r0);
+      cswap(&mut r0, &mut r1, mask);
+    }
+
+    self.from_montgomery(&r0)
+  }
+}
+
+// Swaps |a| and |b| if |mask| is all ones, does nothing if it's zero.
+fn cswap(a: &mut [u32], b: &mut [u32], mask: u32) {
+  for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+    let t = (*x ^ *y) & mask;
+    *x ^= t;
+    *y ^= t;
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sign {
+  Minus,
+  Plus
+}
+
+// An arbitrary-precision signed integer. Zero is always positive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BigInt {
+  sign: Sign,
+  magnitude: BigUint
+}
+
+impl BigInt {
+  pub fn new(sign: Sign, magnitude: BigUint) -> BigInt {
+    let sign = if magnitude.is_zero() { Sign::Plus } else { sign };
+    BigInt { sign: sign, magnitude: magnitude }
+  }
+
+  pub fn sign(&self) -> Sign {
+    self.sign
+  }
+
+  pub fn magnitude(&self) -> &BigUint {
+    &self.magnitude
+  }
+
+  // The value if it isn't negative.
+  pub fn to_biguint(&self) -> Option<BigUint> {
+    match self.sign {
+      Sign::Plus => Some(self.magnitude.clone()),
+      Sign::Minus => None
+    }
+  }
+
+  // Truncating division, like Rust's primitive integers. The remainder has
+  // the sign of |self|.
+  pub fn divmod(&self, divisor: &BigInt) -> (BigInt, BigInt) {
+    let (q, r) = self.magnitude.divmod(&divisor.magnitude);
+    let sign = if self.sign == divisor.sign { Sign::Plus } else { Sign::Minus };
+    (BigInt::new(sign, q), BigInt::new(self.sign, r))
+  }
+
+  // The remainder in 0..modulus.
+  pub fn modulo(&self, modulus: &BigUint) -> BigUint {
+    let r = &self.magnitude % modulus;
+
+    match self.sign {
+      Sign::Minus if !r.is_zero() => modulus - &r,
+      _ => r
+    }
+  }
+
+  // Returns (g, x, y) with g = gcd(self, other) = self * x + other * y.
+  pub fn ext_gcd(&self, other: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let zero = BigInt::from(BigUint::zero());
+    let one = BigInt::from(BigUint::one());
+
+    let (mut r0, mut r1) = (self.clone(), other.clone());
+    let (mut x0, mut x1) = (one.clone(), zero.clone());
+    let (mut y0, mut y1) = (zero, one);
+
+    while !r1.magnitude.is_zero() {
+      let (q, r) = r0.divmod(&r1);
+      r0 = r1;
+      r1 = r;
+
+      let x = &x0 - &(&q * &x1);
+      x0 = x1;
+      x1 = x;
+
+      let y = &y0 - &(&q * &y1);
+      y0 = y1;
+      y1 = y;
+    }
+
+    // Make the gcd positive.
+    if r0.sign == Sign::Minus {
+      return (-r0, -x0, -y0);
+    }
+
+    (r0, x0, y0)
+  }
+}
+
+impl From<BigUint> for BigInt {
+  fn from(n: BigUint) -> BigInt {
+    BigInt::new(Sign::Plus, n)
+  }
+}
+
+impl fmt::Display for BigInt {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.sign {
+      Sign::Minus => write!(f, "-{}", self.magnitude),
+      Sign::Plus => write!(f, "{}", self.magnitude)
+    }
+  }
+}
+
+impl FromStr for BigInt {
+  type Err = ParseBigIntError;
+
+  fn from_str(s: &str) -> Result<BigInt, ParseBigIntError> {
+    if s.starts_with("-") {
+      return Ok(BigInt::new(Sign::Minus, try!(s[1..].parse())));
+    }
+
+    Ok(BigInt::from(try!(s.parse::<BigUint>())))
+  }
+}
+
+impl Neg for BigInt {
+  type Output = BigInt;
+
+  fn neg(self) -> BigInt {
+    let sign = match self.sign {
+      Sign::Minus => Sign::Plus,
+      Sign::Plus => Sign::Minus
+    };
+
+    BigInt::new(sign, self.magnitude)
+  }
+}
+
+impl<'a, 'b> Add<&'b BigInt> for &'a BigInt {
+  type Output = BigInt;
+
+  fn add(self, other: &BigInt) -> BigInt {
+    if self.sign == other.sign {
+      return BigInt::new(self.sign, &self.magnitude + &other.magnitude);
+    }
+
+    // Subtract the smaller magnitude from the larger one.
+    if self.magnitude >= other.magnitude {
+      BigInt::new(self.sign, &self.magnitude - &other.magnitude)
+    } else {
+      BigInt::new(other.sign, &other.magnitude - &self.magnitude)
+    }
+  }
+}
+
+impl<'a, 'b> Sub<&'b BigInt> for &'a BigInt {
+  type Output = BigInt;
+
+  fn sub(self, other: &BigInt) -> BigInt {
+    self + &(-other.clone())
+  }
+}
+
+impl<'a, 'b> Mul<&'b BigInt> for &'a BigInt {
+  type Output = BigInt;
+
+  fn mul(self, other: &BigInt) -> BigInt {
+    let sign = if self.sign == other.sign { Sign::Plus } else { Sign::Minus };
+    BigInt::new(sign, &self.magnitude * &other.magnitude)
+  }
+}
+
+impl<'a, 'b> Div<&'b BigInt> for &'a BigInt {
+  type Output = BigInt;
+
+  fn div(self, other: &BigInt) -> BigInt {
+    self.divmod(other).0
+  }
+}
+
+impl<'a, 'b> Rem<&'b BigInt> for &'a BigInt {
+  type Output = BigInt;
+
+  fn rem(self, other: &BigInt) -> BigInt {
+    self.divmod(other).1
+  }
+}
+
+forward_binop!(BigInt, Add, add);
+forward_binop!(BigInt, Sub, sub);
+forward_binop!(BigInt, Mul, mul);
+forward_binop!(BigInt, Div, div);
+forward_binop!(BigInt, Rem, rem);
+
+#[cfg(test)]
+mod test {
+  use challenge1::Base64Encoder;
+  use challenge2::HexEncoder;
+  use challenge4::read_file;
+  use std::iter::FromIterator;
+  use BigInt;
+  use BigUint;
+  use Montgomery;
+  use ParseBigIntError;
+
+  fn parse(hex: &str) -> BigUint {
+    BigUint::from_hex(hex)
+  }
+
+  #[test]
+  fn test_fixtures() {
+    for line in read_file("fixtures.txt").lines() {
+      let fields = Vec::from_iter(line.split(' '));
+
+      match fields[0] {
+        "add" => assert_eq!(parse(fields[1]) + parse(fields[2]), parse(fields[3])),
+        "sub" => assert_eq!(parse(fields[1]) - parse(fields[2]), parse(fields[3])),
+        "mul" => assert_eq!(parse(fields[1]) * parse(fields[2]), parse(fields[3])),
+        "divmod" => {
+          assert_eq!(parse(fields[1]).divmod(&parse(fields[2])), (parse(fields[3]), parse(fields[4])));
+        }
+        "gcd" => assert_eq!(parse(fields[1]).gcd(&parse(fields[2])), parse(fields[3])),
+        "modinv" => {
+          let expected = if fields[3] == "none" { None } else { Some(parse(fields[3])) };
+          assert_eq!(parse(fields[1]).modinv(&parse(fields[2])), expected);
+        }
+        "modpow" => {
+          let (base, exponent, modulus) = (parse(fields[1]), parse(fields[2]), parse(fields[3]));
+          assert_eq!(base.modpow(&exponent, &modulus), parse(fields[4]));
+
+          if !modulus.is_even() {
+            assert_eq!(base.modpow_ct(&exponent, &modulus), parse(fields[4]));
+          }
+        }
+        op => panic!("unknown operation {}", op)
+      }
+    }
+  }
+
+  #[test]
+  fn test_signed() {
+    for line in read_file("signed.txt").lines() {
+      let n = Vec::from_iter(line.split(' ').map(|field| field.parse::<BigInt>().unwrap()));
+      let (a, b) = (&n[0], &n[1]);
+
+      assert_eq!(a + b, n[2]);
+      assert_eq!(a - b, n[3]);
+      assert_eq!(a * b, n[4]);
+      assert_eq!(a.divmod(b), (n[5].clone(), n[6].clone()));
+
+      // Decimal round trip.
+      assert_eq!(format!("{}", n[4]), line.split(' ').nth(4).unwrap());
+
+      // Bezout coefficients.
+      let (g, x, y) = a.ext_gcd(b);
+      assert_eq!(&(a * &x) + &(b * &y), g);
+      assert_eq!(g, BigInt::from(a.magnitude().gcd(b.magnitude())));
+    }
+  }
+
+  #[test]
+  fn test_conversions() {
+    let n = parse("0102030405060708090a0b0c0d0e0f10");
+    assert_eq!(n.to_hex(), "0102030405060708090a0b0c0d0e0f10");
+    assert_eq!(n.to_bytes_be(), Vec::from_iter(1u8..17));
+    assert_eq!(BigUint::from_bytes_be(&[0, 0, 1, 2]), parse("102"));
+    assert_eq!(parse("abc").to_hex(), "0abc");
+    assert_eq!(n.to_bytes_be_padded(18)[..3].to_vec(), vec!(0, 0, 1));
+
+    let base64 = n.to_base64();
+    assert_eq!(BigUint::from_base64(&base64), n);
+
+    assert_eq!(BigUint::zero().to_bytes_be(), vec!(0));
+    assert_eq!(BigUint::zero().to_hex(), "00");
+    assert_eq!(BigUint::zero().to_bytes_be_padded(2), vec!(0, 0));
+
+    assert_eq!(BigUint::from_u64(0xdeadbeefcafe).to_u64(), Some(0xdeadbeefcafe));
+    assert_eq!((BigUint::one() << 64).to_u64(), None);
+    assert_eq!(format!("{}", BigUint::one() << 100), "1267650600228229401496703205376");
+    assert_eq!("1267650600228229401496703205376".parse(), Ok(BigUint::one() << 100));
+    assert_eq!("12a".parse::<BigUint>(), Err(ParseBigIntError));
+    assert_eq!(format!("{}", BigUint::zero()), "0");
+  }
+
+  #[test]
+  fn test_divmod_add_back() {
+    // The first quotient estimate is one too large, even after the usual
+    // correction, so the subtraction overshoots and needs to be undone.
+    let u = BigUint::from_limbs(vec!(0, 0, 0x80000000, 0x7fffffff));
+    let v = BigUint::from_limbs(vec!(1, 0, 0x80000000));
+    assert_eq!(u.divmod(&v), (parse("fffffffe"), parse("7fffffffffffffff00000002")));
+  }
+
+  #[test]
+  fn test_shifts() {
+    let n = parse("123456789abcdef0123456789abcdef");
+    assert_eq!(n.clone() << 0, n);
+    assert_eq!(n.clone() << 36, parse("123456789abcdef0123456789abcdef000000000"));
+    assert_eq!(n.clone() >> 68, parse("123456789abcde"));
+    assert_eq!(n.clone() >> 200, BigUint::zero());
+    assert_eq!(n.bits(), 121);
+    assert!(n.bit(0) && !n.bit(4) && !n.bit(121));
+  }
+
+  #[test]
+  fn test_montgomery() {
+    // The NIST prime from cryptopals challenge 33.
+    let p = parse("ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f14374fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7edee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf0598da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb9ed529077096966d670c354e4abc9804f1746c08ca237327ffffffffffffffff");
+    let montgomery = Montgomery::new(&p);
+
+    let a = parse("123456789abcdef");
+    let b = &p - &BigUint::from_u64(2);
+    let product = montgomery.mul(&montgomery.to_montgomery(&a), &montgomery.to_montgomery(&b));
+    assert_eq!(montgomery.from_montgomery(&product), &(&a * &b) % &p);
+
+    // Fermat's little theorem.
+    let exponent = &p - &BigUint::one();
+    assert_eq!(a.modpow(&exponent, &p), BigUint::one());
+    assert_eq!(a.modpow_ct(&exponent, &p), BigUint::one());
+    assert_eq!(a.modpow_ct(&BigUint::zero(), &p), BigUint::one());
+  }
+}
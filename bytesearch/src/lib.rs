@@ -0,0 +1,196 @@
+extern crate crossbeam;
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+// Returns a byte for which |pred| holds, trying candidates on |threads| threads
+// and stopping as soon as one of them finds a match.
+pub fn find_byte<P>(threads: usize, pred: P) -> Option<u8> where P: Fn(u8) -> bool + Sync {
+  with_byte_search(threads, |_: &(), byte| pred(byte), |search| search(()))
+}
+
+struct Round<S> {
+  id: usize,
+  state: Option<Arc<S>>,
+  busy: usize,
+  found: Option<u8>,
+  panicked: bool,
+  shutdown: bool
+}
+
+fn lock<'a, S>(round: &'a Mutex<Round<S>>) -> MutexGuard<'a, Round<S>> {
+  match round.lock() {
+    Ok(round) => round,
+    Err(poisoned) => poisoned.into_inner()
+  }
+}
+
+// Sends the workers home, even if the searching thread panics.
+struct Shutdown<'a, S: 'a> {
+  round: &'a Mutex<Round<S>>,
+  work: &'a Condvar
+}
+
+impl<'a, S> Drop for Shutdown<'a, S> {
+  fn drop(&mut self) {
+    let mut round = lock(self.round);
+    round.shutdown = true;
+    self.work.notify_all();
+  }
+}
+
+// A worker's part in the current round. The last worker to finish wakes up
+// the searching thread.
+struct Busy<'a, S: 'a> {
+  round: &'a Mutex<Round<S>>,
+  idle: &'a Condvar,
+  done: &'a AtomicBool
+}
+
+impl<'a, S> Drop for Busy<'a, S> {
+  fn drop(&mut self) {
+    let mut round = lock(self.round);
+
+    // Stop the other workers early, the round failed.
+    if thread::panicking() {
+      round.panicked = true;
+      self.done.store(true, Ordering::SeqCst);
+    }
+
+    round.busy -= 1;
+    if round.busy == 0 {
+      self.idle.notify_one();
+    }
+  }
+}
+
+// Starts a pool of |threads| workers and calls |body| with a search function.
+// Every search hands a new state to the workers, which then try all 256
+// candidate bytes with |pred| and stop early on the first hit. The workers
+// are reused for all searches until |body| returns. If |pred| panics, the
+// search panics too.
+pub fn with_byte_search<S, P, B, R>(threads: usize, pred: P, body: B) -> R
+    where S: Send + Sync, P: Fn(&S, u8) -> bool + Sync, B: FnOnce(&Fn(S) -> Option<u8>) -> R
+{
+  assert!(threads > 0);
+
+  let round = Mutex::new(Round { id: 0, state: None, busy: 0, found: None, panicked: false, shutdown: false });
+  let work = Condvar::new();
+  let idle = Condvar::new();
+  let next = AtomicUsize::new(0);
+  let done = AtomicBool::new(false);
+
+  crossbeam::scope(|scope| {
+    for _ in 0..threads {
+      scope.spawn(|| {
+        let mut seen = 0;
+
+        loop {
+          // Wait for a new round to start.
+          let state = {
+            let mut current = round.lock().unwrap();
+            while current.id == seen && !current.shutdown {
+              current = work.wait(current).unwrap();
+            }
+
+            if current.shutdown {
+              return;
+            }
+
+            seen = current.id;
+            current.state.clone().unwrap()
+          };
+
+          // Check out of the round when done, or when |pred| panics.
+          let _busy = Busy { round: &round, idle: &idle, done: &done };
+
+          while !done.load(Ordering::SeqCst) {
+            // Grab the next untried candidate.
+            let candidate = next.fetch_add(1, Ordering::SeqCst);
+            if candidate > 255 {
+              break;
+            }
+
+            if pred(&state, candidate as u8) {
+              let mut current = round.lock().unwrap();
+              if current.found.is_none() {
+                current.found = Some(candidate as u8);
+              }
+
+              done.store(true, Ordering::SeqCst);
+            }
+          }
+
+        }
+      });
+    }
+
+    let _shutdown = Shutdown { round: &round, work: &work };
+
+    let search = |state: S| {
+      let mut current = round.lock().unwrap();
+      next.store(0, Ordering::SeqCst);
+      done.store(false, Ordering::SeqCst);
+
+      // Start a new round.
+      current.id += 1;
+      current.state = Some(Arc::new(state));
+      current.busy = threads;
+      current.found = None;
+      work.notify_all();
+
+      // Wait until all workers are done.
+      while current.busy > 0 {
+        current = idle.wait(current).unwrap();
+      }
+
+      // Don't poison the lock, the workers still need it to shut down.
+      if current.panicked {
+        drop(current);
+        panic!("a worker panicked");
+      }
+
+      current.state = None;
+      current.found
+    };
+
+    body(&search)
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use std::thread;
+  use find_byte;
+  use with_byte_search;
+
+  #[test]
+  fn test() {
+    assert_eq!(find_byte(4, |byte| byte == 0), Some(0));
+    assert_eq!(find_byte(4, |byte| byte == 0xff), Some(0xff));
+    assert_eq!(find_byte(1, |byte| byte == 0x42), Some(0x42));
+    assert_eq!(find_byte(4, |_| false), None);
+  }
+
+  #[test]
+  fn test_reuse() {
+    let found = with_byte_search(4, |target: &u8, byte| byte == *target, |search| {
+      (0..16).map(|i| search(i * 16)).collect::<Vec<_>>()
+    });
+
+    assert_eq!(found, (0..16).map(|i| Some(i * 16)).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn test_panic() {
+    // A panicking predicate doesn't leave the caller waiting forever.
+    for &threads in [1, 4].iter() {
+      let result = thread::spawn(move || {
+        find_byte(threads, |byte| if byte == 0x80 { panic!("oracle failed") } else { false })
+      }).join();
+
+      assert!(result.is_err());
+    }
+  }
+}
@@ -22,7 +22,7 @@ impl HexDecoder for str {
   }
 }
 
-static CHARS: &'static[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+static CHARS: &'static[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
 
 pub trait Base64Encoder {
   fn to_base64(&self) -> String;
@@ -88,5 +88,6 @@ mod test {
     assert_eq!(b"easure.".to_base64(), "ZWFzdXJlLg==");
     assert_eq!(b"asure.".to_base64(), "YXN1cmUu");
     assert_eq!(b"sure.".to_base64(), "c3VyZS4=");
+    assert_eq!(b"\xfb\xff".to_base64(), "+/8=");
   }
 }
@@ -4,6 +4,9 @@ extern crate challenge9;
 extern crate challenge10;
 
 use rand::{Rng, OsRng};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::f64::NEG_INFINITY;
 use std::iter::{FromIterator, repeat};
 use challenge8::is_ecb_ciphertext;
 use challenge9::PKCS7Pad;
@@ -17,7 +20,160 @@ pub fn is_ecb_blackbox<F>(f: F, blocksize: usize) -> bool
   is_ecb_ciphertext(&ciphertext, blocksize)
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Mode {
+  Ecb,
+  Cbc
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Detection {
+  // None if the mode couldn't be determined.
+  pub mode: Option<Mode>,
+  pub confidence: f64
+}
+
+// A probe of |num_blocks + 1| identical blocks. Whatever the length of a
+// prefix is, at least |num_blocks| of them will be block-aligned.
+pub fn probe_input(blocksize: usize, num_blocks: usize, fill: u8) -> Vec<u8> {
+  Vec::from_iter(repeat(fill).take(blocksize * (num_blocks + 1)))
+}
+
+// Classify the encryption of a probe with |num_blocks| aligned, identical
+// blocks, by comparing how likely the number of repeated blocks is for each
+// mode. The confidence is the posterior probability of the chosen mode,
+// assuming both modes are equally likely. A ciphertext too short to hold
+// the probe tells us nothing.
+pub fn classify_ciphertext(ciphertext: &[u8], blocksize: usize, num_blocks: usize) -> Detection {
+  match log_likelihoods(ciphertext, blocksize, num_blocks) {
+    Some((ecb, cbc)) => decide(ecb, cbc),
+    None => Detection { mode: None, confidence: 0.0 }
+  }
+}
+
+// Classify every call to the oracle on its own, with |num_probes| different
+// probes. This works for oracles that switch modes between calls too.
+pub fn detect_modes<F>(f: F, blocksize: usize, num_probes: usize) -> Vec<Detection>
+    where F: Fn(&[u8]) -> Vec<u8>
+{
+  Vec::from_iter((0..num_probes).map(|i| {
+    let (num_blocks, probe) = probe_for(blocksize, i);
+    classify_ciphertext(&f(&probe), blocksize, num_blocks)
+  }))
+}
+
+// Query the oracle with |num_probes| different probes and combine the
+// evidence, assuming that it always uses the same mode. Oracles that switch
+// modes between calls will be reported as unknown, use detect_modes() for
+// those. Replies too short to hold the probe are skipped.
+pub fn detect_mode<F>(f: F, blocksize: usize, num_probes: usize) -> Detection
+    where F: Fn(&[u8]) -> Vec<u8>
+{
+  let (mut ecb, mut cbc) = (0.0, 0.0);
+  let mut modes = HashSet::new();
+
+  for i in 0..num_probes {
+    let (num_blocks, probe) = probe_for(blocksize, i);
+    if let Some((probe_ecb, probe_cbc)) = log_likelihoods(&f(&probe), blocksize, num_blocks) {
+      modes.insert(decide(probe_ecb, probe_cbc).mode);
+      ecb += probe_ecb;
+      cbc += probe_cbc;
+    }
+  }
+
+  // Bail out if the probes disagree, or none of them counted.
+  if modes.len() != 1 {
+    return Detection { mode: None, confidence: 0.0 };
+  }
+
+  decide(ecb, cbc)
+}
+
+// Vary the number of blocks and the fill byte with every probe.
+fn probe_for(blocksize: usize, i: usize) -> (usize, Vec<u8>) {
+  let num_blocks = 3 + i % 3;
+  (num_blocks, probe_input(blocksize, num_blocks, i as u8))
+}
+
+// Picks the more likely mode, given the log-likelihoods of both.
+fn decide(ecb: f64, cbc: f64) -> Detection {
+  if ecb == NEG_INFINITY && cbc == NEG_INFINITY {
+    // Neither mode explains what we see.
+    return Detection { mode: None, confidence: 0.0 };
+  }
+
+  if ecb >= cbc {
+    Detection { mode: Some(Mode::Ecb), confidence: 1.0 / (1.0 + (cbc - ecb).exp()) }
+  } else {
+    Detection { mode: Some(Mode::Cbc), confidence: 1.0 / (1.0 + (ecb - cbc).exp()) }
+  }
+}
+
+// The log-likelihoods of the number of repeated blocks in |ciphertext|, if
+// the oracle used ECB and if it used CBC. With CBC every repetition is a
+// collision of random blocks. With ECB the |num_blocks| aligned blocks of
+// the probe yield |num_blocks - 1| repetitions, or one more if the block
+// in front of them happens to be aligned too. Everything else collides by
+// chance, just like with CBC. Returns None if |ciphertext| has fewer than
+// |num_blocks| blocks.
+fn log_likelihoods(ciphertext: &[u8], blocksize: usize, num_blocks: usize) -> Option<(f64, f64)> {
+  assert!(num_blocks > 0);
+
+  let total = ciphertext.len() / blocksize;
+  if total < num_blocks {
+    return None;
+  }
+
+  let repeats = count_repeated_blocks(ciphertext, blocksize);
+  let cbc = log_poisson(repeats, expected_collisions(total, blocksize));
+
+  let fixed = num_blocks - 1;
+  let ecb = if repeats < fixed {
+    NEG_INFINITY
+  } else {
+    let rest = expected_collisions(total - fixed, blocksize);
+    let aligned = log_poisson(repeats - fixed, rest);
+    let unaligned = if repeats > fixed { log_poisson(repeats - fixed - 1, rest) } else { NEG_INFINITY };
+
+    // Either case is equally likely.
+    let max = aligned.max(unaligned);
+    if max == NEG_INFINITY {
+      NEG_INFINITY
+    } else {
+      max + (0.5 * ((aligned - max).exp() + (unaligned - max).exp())).ln()
+    }
+  };
+
+  Some((ecb, cbc))
+}
+
+fn count_repeated_blocks(bytes: &[u8], blocksize: usize) -> usize {
+  let mut blocks = HashSet::new();
+  bytes.chunks(blocksize).filter(|block| !blocks.insert(*block)).count()
+}
+
+// The expected number of colliding pairs among |num_blocks| random blocks.
+fn expected_collisions(num_blocks: usize, blocksize: usize) -> f64 {
+  let pairs = (num_blocks * num_blocks.saturating_sub(1) / 2) as f64;
+  pairs * 2f64.powi(-8 * blocksize as i32)
+}
+
+// The log of P(X = k) for X ~ Poisson(lambda).
+fn log_poisson(k: usize, lambda: f64) -> f64 {
+  if lambda == 0.0 {
+    return if k == 0 { 0.0 } else { NEG_INFINITY };
+  }
+
+  let log_factorial = (1..k + 1).fold(0.0, |sum, i| sum + (i as f64).ln());
+  k as f64 * lambda.ln() - lambda - log_factorial
+}
+
 pub fn black_box(input: &[u8]) -> Vec<u8> {
+  black_box_with_mode(input).0
+}
+
+// Like black_box() but also tells which mode was used.
+pub fn black_box_with_mode(input: &[u8]) -> (Vec<u8>, Mode) {
   let mut rng = OsRng::new().unwrap();
 
   // Generate a random key.
@@ -34,11 +190,32 @@ pub fn black_box(input: &[u8]) -> Vec<u8> {
 
   // Choose block cipher mode.
   if rng.gen_range(0, 2) == 0 {
-    aes_128_ecb_encrypt(&key, &data)
+    (aes_128_ecb_encrypt(&key, &data), Mode::Ecb)
   } else {
     let mut iv = [0u8; 16];
     rng.fill_bytes(&mut iv);
-    aes_128_cbc_encrypt(&key, &data, iv.to_vec())
+    (aes_128_cbc_encrypt(&key, &data, iv.to_vec()), Mode::Cbc)
+  }
+}
+
+// Records the mode of every black_box() call.
+pub struct RecordingBlackBox {
+  modes: RefCell<Vec<Mode>>
+}
+
+impl RecordingBlackBox {
+  pub fn new() -> RecordingBlackBox {
+    RecordingBlackBox { modes: RefCell::new(vec!()) }
+  }
+
+  pub fn black_box(&self, input: &[u8]) -> Vec<u8> {
+    let (ciphertext, mode) = black_box_with_mode(input);
+    self.modes.borrow_mut().push(mode);
+    ciphertext
+  }
+
+  pub fn modes(&self) -> Vec<Mode> {
+    self.modes.borrow().clone()
   }
 }
 
@@ -53,14 +230,125 @@ fn random_padding() -> Vec<u8> {
 
 #[cfg(test)]
 mod test {
-  use black_box;
+  use rand::{Rng, OsRng};
+  use std::iter::FromIterator;
+  use challenge9::PKCS7Pad;
+  use challenge10::{aes_128_ecb_encrypt, aes_128_cbc_encrypt};
+  use Detection;
+  use Mode;
+  use RecordingBlackBox;
+  use classify_ciphertext;
+  use detect_mode;
+  use detect_modes;
   use is_ecb_blackbox;
+  use probe_input;
+
+  const TRIALS: usize = 2000;
 
   #[test]
   fn test() {
-    let is_ecb = is_ecb_blackbox(black_box, 16);
+    let blackbox = RecordingBlackBox::new();
+
+    let guesses = Vec::from_iter((0..TRIALS).map(|_| {
+      is_ecb_blackbox(|data| blackbox.black_box(data), 16)
+    }));
+
+    // Compare with what the black box actually did.
+    let modes = blackbox.modes();
+    let correct = guesses.iter().zip(modes.iter()).filter(|&(is_ecb, mode)| {
+      *is_ecb == (*mode == Mode::Ecb)
+    }).count();
+
+    assert_eq!(correct, TRIALS);
+  }
+
+  #[test]
+  fn test_accuracy() {
+    let blackbox = RecordingBlackBox::new();
+    let mut correct = 0;
+    let mut num_ecb = 0;
+
+    for _ in 0..TRIALS {
+      let ciphertext = blackbox.black_box(&probe_input(16, 3, 0));
+      let detection = classify_ciphertext(&ciphertext, 16, 3);
+      let mode = *blackbox.modes().last().unwrap();
+
+      if detection.mode == Some(mode) && detection.confidence > 0.99 {
+        correct += 1;
+      }
+
+      if mode == Mode::Ecb {
+        num_ecb += 1;
+      }
+    }
+
+    // Both modes should have been used about equally often.
+    assert!(num_ecb > TRIALS / 3 && num_ecb < TRIALS * 2 / 3);
+    assert_eq!(correct, TRIALS);
+  }
+
+  #[test]
+  fn test_detect_modes() {
+    let blackbox = RecordingBlackBox::new();
+    let detections = detect_modes(|data| blackbox.black_box(data), 16, TRIALS);
+
+    for (detection, mode) in detections.iter().zip(blackbox.modes().iter()) {
+      assert_eq!(detection.mode, Some(*mode));
+      assert!(detection.confidence > 0.99);
+    }
+  }
+
+  #[test]
+  fn test_confidence() {
+    // With one-byte blocks collisions are common, eight repeated blocks
+    // out of 64 fit both modes.
+    let ciphertext = Vec::from_iter((0..64).map(|i| (i % 56) as u8));
+    let detection = classify_ciphertext(&ciphertext, 1, 3);
+    assert!(detection.mode.is_some());
+    assert!(detection.confidence >= 0.5 && detection.confidence < 0.9);
+
+    // No repeated blocks at all rule out ECB.
+    let ciphertext = Vec::from_iter((0..64).map(|i| i as u8));
+    let detection = classify_ciphertext(&ciphertext, 1, 3);
+    assert_eq!(detection.mode, Some(Mode::Cbc));
+    assert_eq!(detection.confidence, 1.0);
+
+    // A ciphertext too short for the probe is missing data, that's not
+    // evidence for either mode.
+    let detection = classify_ciphertext(&[0; 16], 16, 3);
+    assert_eq!(detection, Detection { mode: None, confidence: 0.0 });
+  }
+
+  #[test]
+  fn test_detect_mode() {
+    let mut rng = OsRng::new().unwrap();
+    let mut key = [0u8; 16];
+    rng.fill_bytes(&mut key);
+
+    let ecb = |data: &[u8]| aes_128_ecb_encrypt(&key, &data.pkcs7_pad(16));
+    let detection = detect_mode(ecb, 16, 8);
+    assert_eq!(detection.mode, Some(Mode::Ecb));
+    assert!(detection.confidence > 0.99);
+
+    let cbc = |data: &[u8]| aes_128_cbc_encrypt(&key, &data.pkcs7_pad(16), key.to_vec());
+    let detection = detect_mode(cbc, 16, 8);
+    assert_eq!(detection.mode, Some(Mode::Cbc));
+    assert!(detection.confidence > 0.99);
+
+    // Truncated replies are skipped, if none are left we don't know.
+    let truncated = |data: &[u8]| aes_128_ecb_encrypt(&key, &data.pkcs7_pad(16))[..32].to_vec();
+    assert_eq!(detect_mode(truncated, 16, 8), Detection { mode: None, confidence: 0.0 });
+
+    // The black box picks a new mode for every call, so the probes only
+    // agree if it happened to use the same mode every time.
+    let blackbox = RecordingBlackBox::new();
+    let detection = detect_mode(|data| blackbox.black_box(data), 16, 16);
+    let modes = blackbox.modes();
 
-    // Yeah that's lame but I manually confirmed it works.
-    assert!(is_ecb || !is_ecb);
+    if modes.iter().all(|mode| *mode == modes[0]) {
+      assert_eq!(detection.mode, Some(modes[0]));
+    } else {
+      assert_eq!(detection.mode, None);
+    }
   }
 }
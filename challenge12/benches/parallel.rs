@@ -0,0 +1,25 @@
+#![feature(test)]
+
+extern crate test;
+extern crate challenge12;
+
+use test::Bencher;
+use challenge12::{BlackBox, decrypt_ecb, decrypt_ecb_parallel};
+
+#[bench]
+fn bench_decrypt_ecb(b: &mut Bencher) {
+  let blackbox = BlackBox::new();
+  b.iter(|| decrypt_ecb(&|data| blackbox.encrypt(data)));
+}
+
+#[bench]
+fn bench_decrypt_ecb_parallel_2(b: &mut Bencher) {
+  let blackbox = BlackBox::new();
+  b.iter(|| decrypt_ecb_parallel(&|data| blackbox.encrypt(data), 2));
+}
+
+#[bench]
+fn bench_decrypt_ecb_parallel_4(b: &mut Bencher) {
+  let blackbox = BlackBox::new();
+  b.iter(|| decrypt_ecb_parallel(&|data| blackbox.encrypt(data), 4));
+}
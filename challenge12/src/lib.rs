@@ -1,4 +1,5 @@
 extern crate rand;
+extern crate bytesearch;
 extern crate challenge6;
 extern crate challenge9;
 extern crate challenge10;
@@ -7,6 +8,8 @@ extern crate challenge11;
 use rand::{Rng, OsRng};
 use std::iter::{FromIterator, repeat};
 use std::ops::Range;
+use std::usize;
+use bytesearch::with_byte_search;
 use challenge6::Base64Decoder;
 use challenge9::PKCS7Pad;
 use challenge10::aes_128_ecb_encrypt;
@@ -43,18 +46,26 @@ impl BlackBox {
 }
 
 pub fn determine_blocksize<F>(f: F) -> usize where F: Fn(&[u8]) -> Vec<u8> {
+  try_determine_blocksize(f, usize::MAX).unwrap()
+}
+
+// Like determine_blocksize(), but gives up after |max_len| bytes of input,
+// e.g. for oracles whose output length doesn't depend on the input.
+pub fn try_determine_blocksize<F>(f: F, max_len: usize) -> Option<usize> where F: Fn(&[u8]) -> Vec<u8> {
   let mut input = vec!();
   let orig = f(&input).len();
 
-  loop {
+  while input.len() < max_len {
     // Append a byte to the input.
     input.push(0);
 
     let current = f(&input).len();
     if current > orig {
-      return current - orig;
+      return Some(current - orig);
     }
   }
+
+  None
 }
 
 pub fn determine_padding<F>(f: F) -> usize where F: Fn(&[u8]) -> Vec<u8> {
@@ -72,6 +83,47 @@ pub fn determine_padding<F>(f: F) -> usize where F: Fn(&[u8]) -> Vec<u8> {
 }
 
 pub fn decrypt_ecb<F>(f: &F) -> Vec<u8> where F: Fn(&[u8]) -> Vec<u8> {
+  decrypt_ecb_with(f, |input, pos, guess, target| {
+    // Try all 256 possible bytes.
+    for byte in 0..256 {
+      // Apply and encrypt our current guess.
+      input[pos] = byte as u8;
+      let encryption = f(input);
+
+      // Stop when we found the correct byte.
+      if encryption[guess.clone()] == encryption[target.clone()] {
+        break;
+      }
+    }
+  })
+}
+
+pub fn decrypt_ecb_parallel<F>(f: &F, threads: usize) -> Vec<u8>
+    where F: Fn(&[u8]) -> Vec<u8> + Sync
+{
+  let pred = |&(ref input, pos, ref guess, ref target): &(Vec<u8>, usize, Range<usize>, Range<usize>), byte| {
+    // Apply and encrypt our current guess.
+    let mut input: Vec<u8> = input.clone();
+    input[pos] = byte;
+    let encryption = f(&input);
+    encryption[guess.clone()] == encryption[target.clone()]
+  };
+
+  with_byte_search(threads, pred, |search| {
+    decrypt_ecb_with(f, |input, pos, guess, target| {
+      // Try all 256 possible bytes on the thread pool.
+      let byte = search((input.to_vec(), pos, guess, target));
+      input[pos] = byte.expect("failed to recover byte");
+    })
+  })
+}
+
+// |guess| is called with the input buffer, the position of the byte to guess,
+// and the ranges of the two ciphertext blocks that must match. It must leave
+// the correct guess in the input buffer.
+fn decrypt_ecb_with<F, G>(f: &F, guess_byte: G) -> Vec<u8>
+    where F: Fn(&[u8]) -> Vec<u8>, G: Fn(&mut [u8], usize, Range<usize>, Range<usize>)
+{
   // First, get the block size.
   let blocksize = determine_blocksize(f);
 
@@ -107,23 +159,18 @@ pub fn decrypt_ecb<F>(f: &F) -> Vec<u8> where F: Fn(&[u8]) -> Vec<u8> {
     let start = secret_len + index - index % blocksize;
     let target = Range { start: start, end: start + blocksize };
 
-    // Try all 256 possible bytes.
-    for byte in 0..256 {
-      // Apply and encrypt our current guess.
-      input[secret_len - 1] = byte as u8;
-      let encryption = f(&input);
-
-      // Stop when we found the correct byte.
-      if encryption[guess.clone()] == encryption[target.clone()] {
-        break;
-      }
-    }
+    // Find the byte that makes both blocks match.
+    guess_byte(&mut input, secret_len - 1, guess.clone(), target);
   }
 
   // Carve the decryption out of the buffer.
   input[padding..secret_len].to_vec()
 }
 
+pub fn gcd(a: usize, b: usize) -> usize {
+  if b == 0 { a } else { gcd(b, a % b) }
+}
+
 #[cfg(test)]
 mod test {
   use challenge6::Base64Decoder;
@@ -131,8 +178,10 @@ mod test {
   use SECRET;
   use BlackBox;
   use decrypt_ecb;
+  use decrypt_ecb_parallel;
   use determine_padding;
   use determine_blocksize;
+  use gcd;
 
   #[test]
   fn test() {
@@ -141,6 +190,20 @@ mod test {
     assert_eq!(decrypted, SECRET.from_base64());
   }
 
+  #[test]
+  fn test_parallel() {
+    let blackbox = BlackBox::new();
+    let decrypted = decrypt_ecb_parallel(&|data| blackbox.encrypt(data), 4);
+    assert_eq!(decrypted, SECRET.from_base64());
+  }
+
+  #[test]
+  fn test_gcd() {
+    assert_eq!(gcd(0, 16), 16);
+    assert_eq!(gcd(144, 160), 16);
+    assert_eq!(gcd(7, 16), 1);
+  }
+
   #[test]
   fn test_ecb() {
     let blackbox = BlackBox::new();
@@ -2,13 +2,16 @@ extern crate openssl;
 extern crate rand;
 extern crate challenge9;
 extern crate challenge10;
+extern crate challenge12;
 
 use openssl::crypto::symm as cipher;
 use rand::{Rng, OsRng};
 use std::collections::HashMap;
 use std::iter::FromIterator;
+use std::str;
 use challenge9::PKCS7Pad;
 use challenge10::aes_128_ecb_encrypt;
+use challenge12::try_determine_blocksize;
 
 pub fn aes_128_ecb_decrypt(key: &[u8], ciphertext: &[u8]) -> Vec<u8> {
   assert!(key.len() == 16 && ciphertext.len() % 16 == 0);
@@ -37,12 +40,7 @@ impl BlackBox {
   }
 
   pub fn profile_for(&self, email: &str) -> Vec<u8> {
-    // Remove meta chars.
-    let email = email.replace("&", "");
-    let email = email.replace("=", "");
-
-    // Encode.
-    let encoded = format!("email={}&uid=10&role=user", email);
+    let encoded = encode_profile(email);
 
     // Pad to block size.
     let data = encoded.as_bytes().pkcs7_pad(16);
@@ -51,25 +49,128 @@ impl BlackBox {
     aes_128_ecb_encrypt(&self.key, &data)
   }
 
-  pub fn decode(&self, profile: &[u8]) -> HashMap<String,String> {
+  pub fn decode(&self, profile: &[u8]) -> Result<HashMap<String,String>, ProfileError> {
     let data = aes_128_ecb_decrypt(&self.key, profile);
-    let encoded = String::from_utf8(data).unwrap();
-    let mut map = HashMap::new();
 
-    for pair in encoded.split("&") {
-      let pair = Vec::from_iter(pair.split("="));
-      if pair.len() == 2 {
-        map.insert(pair[0].to_string(), pair[1].to_string());
-      }
+    match String::from_utf8(data) {
+      Ok(encoded) => parse_profile(&encoded),
+      Err(_) => Err(ProfileError::InvalidUtf8)
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProfileError {
+  InvalidUtf8,
+  MalformedPair(String)
+}
+
+pub fn encode_profile(email: &str) -> String {
+  // Remove meta chars.
+  let email = email.replace("&", "");
+  let email = email.replace("=", "");
+
+  // Encode.
+  format!("email={}&uid=10&role=user", email)
+}
+
+pub fn parse_profile(encoded: &str) -> Result<HashMap<String,String>, ProfileError> {
+  let mut map = HashMap::new();
+
+  for pair in encoded.split("&") {
+    let kv = Vec::from_iter(pair.split("="));
+    if kv.len() != 2 {
+      return Err(ProfileError::MalformedPair(pair.to_string()));
     }
 
-    map
+    map.insert(kv[0].to_string(), kv[1].to_string());
+  }
+
+  Ok(map)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ForgeError {
+  UnknownBlocksize,
+  // The input doesn't show up in the plaintext layout.
+  UnknownInputOffset,
+  // No input puts the target block with the given index in place.
+  UnreachableBlock(usize)
+}
+
+// Forges an ECB encryption of |target| by cutting matching blocks out of the
+// encryptions of chosen inputs and pasting them together. |layout| returns the
+// plaintext that |oracle| encrypts for a given input, including any filtering
+// it applies to the input.
+pub fn forge_ecb<F, L>(oracle: &F, layout: &L, target: &[u8]) -> Result<Vec<u8>, ForgeError>
+    where F: Fn(&str) -> Vec<u8>, L: Fn(&str) -> Vec<u8>
+{
+  // Zeros are valid UTF-8, so the oracle takes challenge12's inputs.
+  let bytes_oracle = |input: &[u8]| oracle(str::from_utf8(input).unwrap());
+  let bs = try!(try_determine_blocksize(bytes_oracle, 64).ok_or(ForgeError::UnknownBlocksize));
+
+  // Find where the input starts in the plaintext.
+  let (a, b) = (layout("A"), layout("B"));
+  let offset = try!(a.iter().zip(b.iter()).position(|(a, b)| a != b).ok_or(ForgeError::UnknownInputOffset));
+
+  let target = target.pkcs7_pad(bs);
+  let mut forged = Vec::with_capacity(target.len());
+
+  for (index, block) in target.chunks(bs).enumerate() {
+    let (input, position) = try!(find_input_for_block(layout, block, offset, bs).ok_or(ForgeError::UnreachableBlock(index)));
+
+    // Cut the block out of the encryption of our chosen input.
+    let encryption = oracle(&input);
+    forged.extend(encryption[position*bs..(position+1)*bs].iter().cloned());
   }
+
+  Ok(forged)
+}
+
+// Looks for an input that makes the plaintext contain |block| at a block
+// boundary. Input bytes falling into that block are taken from |block|, all
+// others are filler. Returns the input and the index of the block.
+fn find_input_for_block<L>(layout: &L, block: &[u8], offset: usize, bs: usize) -> Option<(String, usize)>
+    where L: Fn(&str) -> Vec<u8>
+{
+  let max_len = layout("").len() + bs * 2;
+
+  for len in 0..max_len {
+    for position in 0..(offset + len) / bs + 1 {
+      let range = position * bs..(position + 1) * bs;
+
+      let input = Vec::from_iter((offset..offset+len).map(|i| {
+        if i >= range.start && i < range.end { block[i - range.start] } else { b'A' }
+      }));
+
+      let input = match String::from_utf8(input) {
+        Ok(input) => input,
+        Err(_) => continue
+      };
+
+      // Check that the block survives filtering and ends up in place.
+      let plaintext = layout(&input).pkcs7_pad(bs);
+      if plaintext.len() >= range.end && &plaintext[range] == block {
+        return Some((input, position));
+      }
+    }
+  }
+
+  None
 }
 
 #[cfg(test)]
 mod test {
+  use rand::{Rng, OsRng};
+  use challenge9::PKCS7Pad;
+  use challenge10::aes_128_ecb_encrypt;
   use BlackBox;
+  use ForgeError;
+  use ProfileError;
+  use aes_128_ecb_decrypt;
+  use encode_profile;
+  use forge_ecb;
+  use parse_profile;
 
   #[test]
   fn test() {
@@ -85,18 +186,57 @@ mod test {
     profile.extend(blackbox.profile_for(input)[16..32].to_vec());
 
     // See if we have a role=admin profile.
-    let data = blackbox.decode(&profile);
+    let data = blackbox.decode(&profile).unwrap();
     assert_eq!(data.get("email").unwrap(), &"asdf@asdf.com");
     assert_eq!(data.get("uid").unwrap(), &"10");
     assert_eq!(data.get("role").unwrap(), &"admin");
   }
 
+  #[test]
+  fn test_forge() {
+    let blackbox = BlackBox::new();
+    let oracle = |email: &str| blackbox.profile_for(email);
+    let layout = |email: &str| encode_profile(email).into_bytes();
+
+    // The email length is chosen to put "role=" at the end of a block.
+    let target = b"email=mall@evil.com&uid=10&role=admin";
+    let profile = forge_ecb(&oracle, &layout, target).unwrap();
+
+    let data = blackbox.decode(&profile).unwrap();
+    assert_eq!(data.get("email").unwrap(), &"mall@evil.com");
+    assert_eq!(data.get("uid").unwrap(), &"10");
+    assert_eq!(data.get("role").unwrap(), &"admin");
+  }
+
+  #[test]
+  fn test_forge_other_format() {
+    let mut rng = OsRng::new().unwrap();
+    let mut key = [0u8; 16];
+    rng.fill_bytes(&mut key);
+
+    let layout = |user: &str| {
+      let user = user.replace("&", "").replace("=", "");
+      format!("user={}&perm=read&ts=42", user).into_bytes()
+    };
+    let oracle = |user: &str| aes_128_ecb_encrypt(&key, &layout(user).pkcs7_pad(16));
+
+    let token = forge_ecb(&oracle, &layout, b"user=alice&perm=admin").unwrap();
+    let decrypted = String::from_utf8(aes_128_ecb_decrypt(&key, &token)).unwrap();
+    let data = parse_profile(&decrypted).unwrap();
+    assert_eq!(data.get("user").unwrap(), &"alice");
+    assert_eq!(data.get("perm").unwrap(), &"admin");
+
+    // "m=write" can't be injected, and no input moves "perm=" to the end of a block.
+    let result = forge_ecb(&oracle, &layout, b"user=mallory&perm=write");
+    assert_eq!(result, Err(ForgeError::UnreachableBlock(1)));
+  }
+
   #[test]
   fn test_profile() {
     let blackbox = BlackBox::new();
     let profile = blackbox.profile_for("test@example.com");
 
-    let data = blackbox.decode(&profile);
+    let data = blackbox.decode(&profile).unwrap();
     assert_eq!(data.get("email").unwrap(), &"test@example.com");
     assert_eq!(data.get("uid").unwrap(), &"10");
     assert_eq!(data.get("role").unwrap(), &"user");
@@ -107,7 +247,18 @@ mod test {
     let blackbox = BlackBox::new();
     let profile = blackbox.profile_for("test@example.com&role=admin");
 
-    let data = blackbox.decode(&profile);
+    let data = blackbox.decode(&profile).unwrap();
     assert_eq!(data.get("role").unwrap(), &"user");
   }
+
+  #[test]
+  fn test_parse_errors() {
+    assert_eq!(parse_profile("email=foo&role"), Err(ProfileError::MalformedPair("role".to_string())));
+    assert_eq!(parse_profile("email=foo=bar"), Err(ProfileError::MalformedPair("email=foo=bar".to_string())));
+
+    // A plaintext that isn't valid UTF-8.
+    let blackbox = BlackBox::new();
+    let profile = aes_128_ecb_encrypt(&blackbox.key, &b"email=\xff&uid=10&role=user".pkcs7_pad(16));
+    assert_eq!(blackbox.decode(&profile), Err(ProfileError::InvalidUtf8));
+  }
 }
@@ -1,12 +1,21 @@
 extern crate rand;
 extern crate challenge6;
+extern crate challenge8;
 extern crate challenge11;
 extern crate challenge12;
 
 use rand::{Rng, OsRng};
+use std::cell::Cell;
 use std::iter::{FromIterator, repeat};
+use challenge8::is_ecb_ciphertext;
 use challenge11::is_ecb_blackbox;
-use challenge12::determine_blocksize;
+use challenge12::{determine_blocksize, gcd};
+
+// The largest block size we try to detect.
+const MAX_BLOCKSIZE: usize = 32;
+
+// How often we re-check the prefix alignment to tell fixed from random prefixes.
+const PREFIX_CHECKS: usize = 8;
 
 pub struct BlackBox {
   blackbox: challenge12::BlackBox,
@@ -33,7 +42,29 @@ impl BlackBox {
   }
 }
 
-pub fn determine_prefix_len<F>(f: F, blocksize: usize) -> usize
+pub struct RandomPrefixBlackBox {
+  blackbox: challenge12::BlackBox
+}
+
+impl RandomPrefixBlackBox {
+  pub fn new() -> RandomPrefixBlackBox {
+    RandomPrefixBlackBox { blackbox: challenge12::BlackBox::new() }
+  }
+
+  pub fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+    let mut rng = OsRng::new().unwrap();
+
+    // Generate a new random prefix for every call.
+    let mut prefix = [0u8; 64];
+    rng.fill_bytes(&mut prefix);
+
+    let mut data = prefix[..rng.gen_range(0, 65)].to_vec();
+    data.extend(input.to_vec());
+    self.blackbox.encrypt(&data)
+  }
+}
+
+pub fn determine_prefix_len<F>(f: F, blocksize: usize) -> Result<usize, Error>
     where F: Fn(&[u8]) -> Vec<u8>
 {
   let mut rng = OsRng::new().unwrap();
@@ -45,30 +76,35 @@ pub fn determine_prefix_len<F>(f: F, blocksize: usize) -> usize
   let mut data = block.to_vec();
   data.extend(block.to_vec());
 
-  loop {
+  // One of |blocksize| alignments must produce two identical blocks.
+  for _ in 0..blocksize {
     let encryption = f(&data);
     let blocks = Vec::from_iter(encryption.chunks(blocksize));
 
     // Find two adjacent, identical ciphertext blocks.
-    for (i, pair) in blocks.windows(2).enumerate() {
-      if pair[0] == pair[1] {
-        return i * blocksize - (data.len() - blocksize * 2);
-      }
+    // The marker blocks can't start before the padding we inserted, unless
+    // the oracle isn't what we think it is.
+    if let Some(i) = blocks.windows(2).position(|pair| pair[0] == pair[1]) {
+      return (i * blocksize).checked_sub(data.len() - blocksize * 2).ok_or(Error::UnknownPrefixLength);
     }
 
     data.insert(0, 0);
   }
+
+  Err(Error::UnknownPrefixLength)
 }
 
-pub fn decrypt_ecb<F>(f: &F) -> Vec<u8> where F: Fn(&[u8]) -> Vec<u8> {
+pub fn decrypt_ecb<F>(f: &F) -> Result<Vec<u8>, Error> where F: Fn(&[u8]) -> Vec<u8> {
   // First, get the block size.
   let blocksize = determine_blocksize(f);
 
   // This attack works for ECB only.
-  assert!(is_ecb_blackbox(f, blocksize));
+  if !is_ecb_blackbox(f, blocksize) {
+    return Err(Error::NotEcb);
+  }
 
   // Determine the length of the random prefix.
-  let prefix_len = determine_prefix_len(f, blocksize);
+  let prefix_len = try!(determine_prefix_len(f, blocksize));
   // The padding needed to align the prefix with the block size.
   let prefix_pad = blocksize - prefix_len % blocksize;
   // The number of blocks the aligned padding fills.
@@ -79,30 +115,264 @@ pub fn decrypt_ecb<F>(f: &F) -> Vec<u8> where F: Fn(&[u8]) -> Vec<u8> {
   // The padding we'll use to align the prefix to the block size.
   let pad = Vec::from_iter(repeat(0).take(prefix_pad));
 
-  challenge12::decrypt_ecb(&|data| {
+  Ok(challenge12::decrypt_ecb(&|data| {
     // Concat pad and given data.
     let mut input = pad.clone();
     input.extend(data.to_vec());
 
     // Remove the prefix blocks.
     f(&input)[prefix..].to_vec()
-  })
+  }))
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+  QueryLimitExceeded,
+  UnknownBlocksize,
+  UnknownPrefixLength,
+  UnknownSuffixLength,
+  NotEcb,
+  NoMatch(usize)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Layout {
+  pub blocksize: usize,
+  // None if the prefix changes its length with every call.
+  pub prefix_len: Option<usize>,
+  pub suffix_len: usize
+}
+
+pub fn determine_layout<F>(f: &F, max_queries: usize) -> Result<Layout, Error>
+    where F: Fn(&[u8]) -> Vec<u8>
+{
+  let engine = try!(Engine::new(f, max_queries));
+  let suffix_len = try!(engine.determine_suffix_len());
+  Ok(Layout { blocksize: engine.blocksize, prefix_len: engine.prefix_len, suffix_len: suffix_len })
+}
+
+pub fn decrypt_ecb_oracle<F>(f: &F, max_queries: usize) -> Result<Vec<u8>, Error>
+    where F: Fn(&[u8]) -> Vec<u8>
+{
+  let engine = try!(Engine::new(f, max_queries));
+  let suffix_len = try!(engine.determine_suffix_len());
+  let bs = engine.blocksize;
+
+  let mut secret = Vec::with_capacity(suffix_len);
+
+  for index in 0..suffix_len {
+    // Align the secret so that the byte we're looking for is the last one of
+    // its block. The first |bs - 1| bytes of that block are known.
+    let filler = Vec::from_iter(repeat(0u8).take(bs - 1 - index % bs));
+    let mut known = filler.clone();
+    known.extend(secret.iter().cloned());
+    let known = &known[known.len()-(bs-1)..];
+
+    // Build a dictionary of all 256 possible blocks and send it together
+    // with the filler, so that a single query tells us the next byte.
+    let mut input = Vec::with_capacity(256 * bs + filler.len());
+    for byte in 0..256 {
+      input.extend(known.iter().cloned());
+      input.push(byte as u8);
+    }
+    input.extend(filler);
+
+    let encryption = try!(engine.query(&input));
+    let target = (256 + index / bs) * bs;
+    let target = &encryption[target..target+bs];
+
+    // Look the target block up in the dictionary.
+    match (0..256).find(|byte| &encryption[byte*bs..(byte+1)*bs] == target) {
+      Some(byte) => secret.push(byte as u8),
+      None => return Err(Error::NoMatch(index))
+    }
+  }
+
+  Ok(secret)
+}
+
+// Wraps an oracle of the form E(prefix || input || suffix) and turns it into
+// E(input || suffix) by prepending a random marker block twice and cutting off
+// everything up to and including the encrypted marker.
+struct Engine<'a, F: 'a> {
+  f: &'a F,
+  queries: Cell<usize>,
+  max_queries: usize,
+  blocksize: usize,
+  marker: Vec<u8>,
+  encrypted_marker: Vec<u8>,
+  // The number of bytes needed to align the marker with a block boundary.
+  alignment: Cell<usize>,
+  prefix_len: Option<usize>
+}
+
+impl<'a, F> Engine<'a, F> where F: Fn(&[u8]) -> Vec<u8> {
+  fn new(f: &'a F, max_queries: usize) -> Result<Engine<'a, F>, Error> {
+    let mut engine = Engine {
+      f: f,
+      queries: Cell::new(0),
+      max_queries: max_queries,
+      blocksize: 0,
+      marker: vec!(),
+      encrypted_marker: vec!(),
+      alignment: Cell::new(0),
+      prefix_len: None
+    };
+
+    engine.blocksize = try!(engine.determine_blocksize());
+
+    // Four identical blocks yield at least three identical ciphertext blocks
+    // for any prefix, if the oracle uses ECB.
+    let input = Vec::from_iter(repeat(0u8).take(engine.blocksize * 4));
+    if !is_ecb_ciphertext(&try!(engine.query_raw(&input)), engine.blocksize) {
+      return Err(Error::NotEcb);
+    }
+
+    // Generate a random marker block.
+    let mut rng = OsRng::new().unwrap();
+    let mut marker = Vec::from_iter(repeat(0u8).take(engine.blocksize));
+    rng.fill_bytes(&mut marker);
+    engine.marker = marker;
+
+    try!(engine.calibrate());
+    Ok(engine)
+  }
+
+  fn query_raw(&self, input: &[u8]) -> Result<Vec<u8>, Error> {
+    if self.queries.get() >= self.max_queries {
+      return Err(Error::QueryLimitExceeded);
+    }
+
+    self.queries.set(self.queries.get() + 1);
+    Ok((self.f)(input))
+  }
+
+  // All ciphertext lengths are multiples of the block size. Growing the input
+  // byte by byte will give us at least two different lengths.
+  fn determine_blocksize(&self) -> Result<usize, Error> {
+    let mut blocksize = 0;
+
+    for len in 0..MAX_BLOCKSIZE*2+1 {
+      let input = Vec::from_iter(repeat(0u8).take(len));
+      blocksize = gcd(blocksize, try!(self.query_raw(&input)).len());
+    }
+
+    if blocksize < 2 || blocksize > MAX_BLOCKSIZE {
+      return Err(Error::UnknownBlocksize);
+    }
+
+    Ok(blocksize)
+  }
+
+  // Find the position of two adjacent blocks that are equal to |block|, or
+  // any two adjacent, identical blocks if no |block| is given.
+  fn find_marker(&self, encryption: &[u8], block: Option<&[u8]>) -> Option<usize> {
+    let blocks = Vec::from_iter(encryption.chunks(self.blocksize));
+
+    blocks.windows(2).position(|pair| {
+      pair[0] == pair[1] && block.map_or(true, |block| pair[0] == block)
+    })
+  }
+
+  fn marked_input(&self, alignment: usize, input: &[u8]) -> Vec<u8> {
+    let mut data = Vec::from_iter(repeat(0u8).take(alignment));
+    data.extend(self.marker.iter().cloned());
+    data.extend(self.marker.iter().cloned());
+    data.extend(input.iter().cloned());
+    data
+  }
+
+  // Find the encryption of the marker block, the alignment needed for the
+  // prefix, and whether the prefix length is fixed.
+  fn calibrate(&mut self) -> Result<(), Error> {
+    let bs = self.blocksize;
+
+    // Without any other input the only identical adjacent blocks will be our
+    // marker blocks, once they're aligned. Random prefixes might need a few
+    // rounds until we hit an alignment.
+    let mut found = None;
+    while found.is_none() {
+      for alignment in 0..bs {
+        let encryption = try!(self.query_raw(&self.marked_input(alignment, b"")));
+        if let Some(position) = self.find_marker(&encryption, None) {
+          found = Some((alignment, position, encryption));
+          break;
+        }
+      }
+    }
+
+    let (alignment, position, encryption) = found.unwrap();
+    self.encrypted_marker = encryption[position*bs..(position+1)*bs].to_vec();
+    self.alignment.set(alignment);
+
+    // A fixed prefix puts the marker at the same position every time.
+    for _ in 0..PREFIX_CHECKS {
+      let encryption = try!(self.query_raw(&self.marked_input(alignment, b"")));
+      if self.find_marker(&encryption, Some(&self.encrypted_marker[..])) != Some(position) {
+        return Ok(());
+      }
+    }
+
+    self.prefix_len = Some(position * bs - alignment);
+    Ok(())
+  }
+
+  // Query the oracle with the prefix removed. Prefixes with random lengths
+  // need several attempts until the marker is block-aligned.
+  fn query(&self, input: &[u8]) -> Result<Vec<u8>, Error> {
+    let bs = self.blocksize;
+
+    loop {
+      for offset in 0..bs {
+        let alignment = (self.alignment.get() + offset) % bs;
+        let encryption = try!(self.query_raw(&self.marked_input(alignment, input)));
+
+        if let Some(position) = self.find_marker(&encryption, Some(&self.encrypted_marker[..])) {
+          // Remember the alignment, it's a good first guess next time.
+          self.alignment.set(alignment);
+          return Ok(encryption[(position+2)*bs..].to_vec());
+        }
+      }
+    }
+  }
+
+  // Grow the input until a full block of padding is added to find out how
+  // many bytes the suffix has.
+  fn determine_suffix_len(&self) -> Result<usize, Error> {
+    let orig = try!(self.query(b"")).len();
+
+    for len in 1..self.blocksize+1 {
+      let input = Vec::from_iter(repeat(0u8).take(len));
+      if try!(self.query(&input)).len() > orig {
+        return Ok(orig - len);
+      }
+    }
+
+    Err(Error::UnknownSuffixLength)
+  }
 }
 
 #[cfg(test)]
 mod test {
+  use std::iter::FromIterator;
   use challenge6::Base64Decoder;
   use challenge11::is_ecb_blackbox;
+  use challenge12;
   use challenge12::{determine_blocksize, SECRET};
   use BlackBox;
+  use RandomPrefixBlackBox;
+  use Error;
+  use Layout;
   use decrypt_ecb;
+  use decrypt_ecb_oracle;
+  use determine_layout;
   use determine_prefix_len;
 
   #[test]
   fn test() {
     let blackbox = BlackBox::new();
     let decrypted = decrypt_ecb(&|data| blackbox.encrypt(data));
-    assert_eq!(decrypted, SECRET.from_base64());
+    assert_eq!(decrypted, Ok(SECRET.from_base64()));
   }
 
   #[test]
@@ -123,6 +393,56 @@ mod test {
     let blackbox = BlackBox::new();
     let blocksize = determine_blocksize(|data| blackbox.encrypt(data));
     let prefix_len = determine_prefix_len(|data| blackbox.encrypt(data), blocksize);
-    assert_eq!(prefix_len, blackbox.prefix.len());
+    assert_eq!(prefix_len, Ok(blackbox.prefix.len()));
+
+    // Identical blocks in front of our input.
+    let oracle = |data: &[u8]| if data.len() > 32 { vec!(0; 64) } else { Vec::from_iter(0..64) };
+    assert_eq!(determine_prefix_len(oracle, 16), Err(Error::UnknownPrefixLength));
+
+    // No identical blocks at all.
+    let oracle = |data: &[u8]| Vec::from_iter((0..data.len() + 16).map(|i| i as u8));
+    assert_eq!(determine_prefix_len(oracle, 16), Err(Error::UnknownPrefixLength));
+  }
+
+  #[test]
+  fn test_oracle() {
+    let blackbox = challenge12::BlackBox::new();
+    let decrypted = decrypt_ecb_oracle(&|data| blackbox.encrypt(data), 10000);
+    assert_eq!(decrypted, Ok(SECRET.from_base64()));
+
+    let blackbox = BlackBox::new();
+    let decrypted = decrypt_ecb_oracle(&|data| blackbox.encrypt(data), 10000);
+    assert_eq!(decrypted, Ok(SECRET.from_base64()));
+  }
+
+  #[test]
+  fn test_oracle_random_prefix() {
+    let blackbox = RandomPrefixBlackBox::new();
+    let decrypted = decrypt_ecb_oracle(&|data| blackbox.encrypt(data), 100000);
+    assert_eq!(decrypted, Ok(SECRET.from_base64()));
+  }
+
+  #[test]
+  fn test_layout() {
+    let secret_len = SECRET.from_base64().len();
+
+    let blackbox = BlackBox::new();
+    let layout = determine_layout(&|data| blackbox.encrypt(data), 10000);
+    assert_eq!(layout, Ok(Layout { blocksize: 16, prefix_len: Some(blackbox.prefix.len()), suffix_len: secret_len }));
+
+    let blackbox = RandomPrefixBlackBox::new();
+    let layout = determine_layout(&|data| blackbox.encrypt(data), 100000);
+    assert_eq!(layout, Ok(Layout { blocksize: 16, prefix_len: None, suffix_len: secret_len }));
+  }
+
+  #[test]
+  fn test_oracle_errors() {
+    let blackbox = RandomPrefixBlackBox::new();
+    let decrypted = decrypt_ecb_oracle(&|data| blackbox.encrypt(data), 100);
+    assert_eq!(decrypted, Err(Error::QueryLimitExceeded));
+
+    // A stream cipher doesn't have a block size.
+    let decrypted = decrypt_ecb_oracle(&|data: &[u8]| data.to_vec(), 1000);
+    assert_eq!(decrypted, Err(Error::UnknownBlocksize));
   }
 }
@@ -5,6 +5,7 @@ extern crate challenge9;
 extern crate challenge10;
 
 use rand::{Rng, OsRng};
+use std::iter::{FromIterator, repeat};
 use challenge2::Xor;
 use challenge7::aes_128_ecb_decrypt;
 use challenge9::PKCS7Pad;
@@ -85,15 +86,117 @@ impl BlackBox {
     let needle = b";admin=true;";
     let decryption = aes_128_cbc_decrypt(&self.key, &data);
 
-    (0..decryption.len()-needle.len()).any(|i| {
-      &decryption[i..i+needle.len()] == needle
-    })
+    decryption.windows(needle.len()).any(|window| window == needle)
   }
 }
 
+#[derive(Debug, PartialEq)]
+pub enum BitFlipError {
+  // There's no allowed placeholder for the given byte.
+  NoPlaceholder(u8),
+  // The oracle takes strings, but the input isn't valid UTF-8.
+  NotUtf8
+}
+
+#[derive(Debug)]
+pub struct BitFlip {
+  pub ciphertext: Vec<u8>,
+  // The plaintext blocks that turn into garbage.
+  pub scrambled_blocks: Vec<usize>
+}
+
+// Replaces every forbidden byte of |inject| with an allowed ASCII byte that
+// differs in as few bits as possible.
+pub fn placeholder_for(inject: &[u8], forbidden: &[u8]) -> Result<Vec<u8>, BitFlipError> {
+  let mut placeholder = Vec::with_capacity(inject.len());
+
+  for byte in inject {
+    if !forbidden.contains(byte) {
+      placeholder.push(*byte);
+      continue;
+    }
+
+    let mut masks = Vec::from_iter(1u8..128);
+    masks.sort_by(|a, b| a.count_ones().cmp(&b.count_ones()));
+
+    match masks.iter().map(|mask| byte ^ mask).find(|b| *b < 128 && !forbidden.contains(b)) {
+      Some(b) => placeholder.push(b),
+      None => return Err(BitFlipError::NoPlaceholder(*byte))
+    }
+  }
+
+  Ok(placeholder)
+}
+
+// Injects |inject| into the plaintext behind a prefix of |prefix_len| bytes.
+// Forbidden bytes are submitted as placeholders and flipped into place via
+// the previous ciphertext block, which scrambles the previous plaintext
+// block. |encrypt| must return the IV followed by the ciphertext.
+//
+// Injections can span several blocks. The first block is flipped via the
+// block in front of it. Every later block with bytes to flip gets its own
+// sacrificial block in front, so the injection ends up split into blocks
+// with garbage in between. Anything that must stay intact has to fit into a
+// single block of the injection.
+pub fn cbc_bitflip<F>(encrypt: &F, prefix_len: usize, inject: &[u8], forbidden: &[u8]) -> Result<BitFlip, BitFlipError>
+    where F: Fn(&str) -> Vec<u8>
+{
+  let placeholder = try!(placeholder_for(inject, forbidden));
+
+  // Align the injection with a block boundary.
+  let filler = (16 - prefix_len % 16) % 16;
+  let mut input = Vec::from_iter(repeat(b'A').take(filler));
+
+  // The plaintext position of the current block.
+  let mut start = prefix_len + filler;
+
+  // The positions and deltas of the bytes we need to flip.
+  let mut flips = vec!();
+  let mut scrambled_blocks = vec!();
+
+  for (n, (chunk, target)) in placeholder.chunks(16).zip(inject.chunks(16)).enumerate() {
+    if chunk != target {
+      // Don't scramble the previous block of the injection.
+      if n > 0 {
+        input.extend(repeat(b'A').take(16));
+        start += 16;
+      }
+
+      // The ciphertext starts with the IV, so flipping bytes of plaintext
+      // block |b| means changing ciphertext block |b|.
+      for (i, (a, b)) in chunk.iter().zip(target.iter()).enumerate() {
+        if a != b {
+          flips.push((start + i, a ^ b));
+        }
+      }
+
+      // The block in front is scrambled, unless we changed the IV.
+      if start >= 16 {
+        scrambled_blocks.push(start / 16 - 1);
+      }
+    }
+
+    input.extend(chunk.iter().cloned());
+    start += chunk.len();
+  }
+
+  let input = try!(String::from_utf8(input).map_err(|_| BitFlipError::NotUtf8));
+  let mut ciphertext = encrypt(&input);
+
+  for (position, delta) in flips {
+    ciphertext[position] ^= delta;
+  }
+
+  Ok(BitFlip { ciphertext: ciphertext, scrambled_blocks: scrambled_blocks })
+}
+
 #[cfg(test)]
 mod test {
+  use std::iter::FromIterator;
+  use BitFlipError;
   use BlackBox;
+  use cbc_bitflip;
+  use placeholder_for;
 
   #[test]
   fn test() {
@@ -121,5 +224,51 @@ mod test {
 
     let encrypted = blackbox.encrypt(";admin=true;");
     assert!(!blackbox.is_admin(&encrypted));
+
+    // Too short for the needle.
+    assert!(!blackbox.is_admin(&encrypted[..32]));
+  }
+
+  #[test]
+  fn test_bitflip() {
+    let blackbox = BlackBox::new();
+    let encrypt = |input: &str| blackbox.encrypt(input);
+
+    let forged = cbc_bitflip(&encrypt, 32, b";admin=true;", b";=").unwrap();
+    assert!(blackbox.is_admin(&forged.ciphertext));
+    assert_eq!(forged.scrambled_blocks, vec!(1));
+
+    // Longer injections can span block boundaries.
+    let forged = cbc_bitflip(&encrypt, 32, b";admin=true;and this spans three blocks", b";=").unwrap();
+    assert!(blackbox.is_admin(&forged.ciphertext));
+    assert_eq!(forged.scrambled_blocks, vec!(1));
+
+    // Bytes to flip in later blocks need a sacrificial block each, the
+    // injection is split at every block boundary.
+    let forged = cbc_bitflip(&encrypt, 32, b";admin=true;AAAArole=admin;uid=0;", b";=").unwrap();
+    assert!(blackbox.is_admin(&forged.ciphertext));
+    assert_eq!(forged.scrambled_blocks, vec!(1, 3, 5));
+
+    // The oracle only takes strings.
+    let forged = cbc_bitflip(&encrypt, 32, b"\xff;admin=true;", b";=");
+    assert_eq!(forged.unwrap_err(), BitFlipError::NotUtf8);
+  }
+
+  #[test]
+  fn test_bitflip_unaligned() {
+    let blackbox = BlackBox::new();
+    let encrypt = |input: &str| blackbox.encrypt(&format!("hello{}", input));
+
+    // A prefix of 37 bytes needs 11 bytes of filler.
+    let forged = cbc_bitflip(&encrypt, 37, b";admin=true;", b";=").unwrap();
+    assert!(blackbox.is_admin(&forged.ciphertext));
+    assert_eq!(forged.scrambled_blocks, vec!(2));
+  }
+
+  #[test]
+  fn test_placeholder() {
+    assert_eq!(placeholder_for(b";admin=true;", b";="), Ok(b":admin<true:".to_vec()));
+    assert_eq!(placeholder_for(b"abc", b""), Ok(b"abc".to_vec()));
+    assert_eq!(placeholder_for(b"a", &Vec::from_iter(0u8..128)), Err(BitFlipError::NoPlaceholder(b'a')));
   }
 }
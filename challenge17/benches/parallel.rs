@@ -0,0 +1,28 @@
+#![feature(test)]
+
+extern crate test;
+extern crate challenge17;
+
+use test::Bencher;
+use challenge17::{BlackBox, recover_plaintext, recover_plaintext_parallel};
+
+#[bench]
+fn bench_recover_plaintext(b: &mut Bencher) {
+  let blackbox = BlackBox::new();
+  let encrypted = blackbox.encrypt();
+  b.iter(|| recover_plaintext(&encrypted, |data| blackbox.is_valid_padding(data)));
+}
+
+#[bench]
+fn bench_recover_plaintext_parallel_2(b: &mut Bencher) {
+  let blackbox = BlackBox::new();
+  let encrypted = blackbox.encrypt();
+  b.iter(|| recover_plaintext_parallel(&encrypted, |data| blackbox.is_valid_padding(data), 2));
+}
+
+#[bench]
+fn bench_recover_plaintext_parallel_4(b: &mut Bencher) {
+  let blackbox = BlackBox::new();
+  let encrypted = blackbox.encrypt();
+  b.iter(|| recover_plaintext_parallel(&encrypted, |data| blackbox.is_valid_padding(data), 4));
+}
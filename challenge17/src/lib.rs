@@ -1,10 +1,12 @@
 extern crate rand;
+extern crate bytesearch;
 extern crate challenge6;
 extern crate challenge9;
 extern crate challenge15;
 extern crate challenge16;
 
 use rand::{Rng, OsRng};
+use bytesearch::with_byte_search;
 use challenge6::Base64Decoder;
 use challenge9::PKCS7Pad;
 use challenge15::PKCS7Unpad;
@@ -67,6 +69,34 @@ impl BlackBox {
 
 pub fn recover_plaintext<F>(data: &[u8], is_valid_pad: F) -> Vec<u8>
     where F: Fn(&[u8]) -> bool
+{
+  recover_plaintext_with(data, |data, pad| {
+    recover_plaintext_byte(data, pad, &is_valid_pad)
+  })
+}
+
+pub fn recover_plaintext_parallel<F>(data: &[u8], is_valid_pad: F, threads: usize) -> Vec<u8>
+    where F: Fn(&[u8]) -> bool + Sync
+{
+  let pred = |&(ref data, pos): &(Vec<u8>, usize), byte| {
+    let mut data: Vec<u8> = data.clone();
+
+    // Apply current guess.
+    data[pos] ^= byte;
+    is_valid_guess(&data, pos, &is_valid_pad)
+  };
+
+  with_byte_search(threads, pred, |search| {
+    recover_plaintext_with(data, |data, pad| {
+      // Try all possible bytes on the thread pool.
+      let byte = search((prepare_padding(data, pad), 16 - pad));
+      byte.expect("failed to recover plaintext byte")
+    })
+  })
+}
+
+fn recover_plaintext_with<R>(data: &[u8], recover_byte: R) -> Vec<u8>
+    where R: Fn(&[u8], usize) -> u8
 {
   assert!(data.len() >= 32);
   let num_blocks = data.len() / 16;
@@ -80,7 +110,7 @@ pub fn recover_plaintext<F>(data: &[u8], is_valid_pad: F) -> Vec<u8>
     // For every byte in a 128-bit block...
     for pad in 1..17 {
       // Save the recovered byte.
-      block.insert(0, recover_plaintext_byte(&tmp, pad, &is_valid_pad));
+      block.insert(0, recover_byte(&tmp, pad));
 
       // Apply the correct guess permanently to recover prior bytes.
       tmp[16 - pad] ^= block[0];
@@ -94,19 +124,9 @@ pub fn recover_plaintext<F>(data: &[u8], is_valid_pad: F) -> Vec<u8>
 pub fn recover_plaintext_byte<F>(data: &[u8], pad: usize, is_valid_pad: &F) -> u8
     where F: Fn(&[u8]) -> bool
 {
-  let mut data = data.to_vec();
+  let data = prepare_padding(data, pad);
   let pos = 16 - pad;
 
-  // Scramble every byte prior to the one we're trying to recover to avoid
-  // (accidentally) valid padding byte series messing with our guesses.
-  let mut rng = OsRng::new().unwrap();
-  rng.fill_bytes(&mut data[0..pos]);
-
-  // Apply the current padding byte.
-  for x in pos..16 {
-    data[x] ^= pad as u8;
-  }
-
   // Try all possible bytes.
   for byte in 0us..256 {
     let mut data = data.clone();
@@ -114,7 +134,7 @@ pub fn recover_plaintext_byte<F>(data: &[u8], pad: usize, is_valid_pad: &F) -> u
     // Apply current guess.
     data[pos] ^= byte as u8;
 
-    if is_valid_pad(&data) {
+    if is_valid_guess(&data, pos, is_valid_pad) {
       return byte as u8;
     }
   }
@@ -122,10 +142,47 @@ pub fn recover_plaintext_byte<F>(data: &[u8], pad: usize, is_valid_pad: &F) -> u
   panic!("failed to recover plaintext byte");
 }
 
+fn is_valid_guess<F>(data: &[u8], pos: usize, is_valid_pad: &F) -> bool
+    where F: Fn(&[u8]) -> bool
+{
+  if !is_valid_pad(data) {
+    return false;
+  }
+
+  // For the last byte, "\x02\x02" or longer paddings might be valid too if the
+  // scrambled bytes happen to match. Changing the byte before must not
+  // invalidate a real "\x01" padding.
+  if pos == 15 {
+    let mut data = data.to_vec();
+    data[pos - 1] ^= 1;
+    return is_valid_pad(&data);
+  }
+
+  true
+}
+
+fn prepare_padding(data: &[u8], pad: usize) -> Vec<u8> {
+  let mut data = data.to_vec();
+  let pos = 16 - pad;
+
+  // Scramble every byte prior to the one we're trying to recover to avoid
+  // (accidentally) valid padding byte series messing with our guesses.
+  let mut rng = OsRng::new().unwrap();
+  rng.fill_bytes(&mut data[0..pos]);
+
+  // Apply the current padding byte.
+  for x in pos..16 {
+    data[x] ^= pad as u8;
+  }
+
+  data
+}
+
 #[cfg(test)]
 mod test {
   use BlackBox;
   use recover_plaintext;
+  use recover_plaintext_parallel;
 
   #[test]
   fn test() {
@@ -139,4 +196,16 @@ mod test {
 
     assert_eq!(blackbox.secret, decrypted);
   }
+
+  #[test]
+  fn test_parallel() {
+    let blackbox = BlackBox::new();
+    let encrypted = blackbox.encrypt();
+
+    let decrypted = recover_plaintext_parallel(&encrypted, |data| {
+      blackbox.is_valid_padding(data)
+    }, 4);
+
+    assert_eq!(blackbox.secret, decrypted);
+  }
 }
@@ -1,29 +1,130 @@
-extern crate challenge2;
+extern crate challenge1;
 extern crate challenge6;
 extern crate challenge10;
 
-use std::iter::FromIterator;
-use std::mem;
-use challenge2::Xor;
 use challenge10::aes_128_ecb_encrypt;
 
-pub fn aes_128_ctr_transform(key: &[u8], nonce: u64, plaintext: &[u8]) -> Vec<u8> {
-  let mut iv = [nonce.to_le(), 0u64];
-  let buf: &[u8; 16] = unsafe { mem::transmute(&iv) };
-  let num_blocks = (plaintext.len() as u64 / 16) + 1;
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Endian {
+  Little,
+  Big
+}
+
+// AES-128 in CTR mode. The counter occupies the last |width| bytes of the
+// counter block, everything in front of it (the nonce) stays fixed. The
+// counter wraps around within its field, just like GCM's inc32.
+pub struct AesCtr {
+  key: Vec<u8>,
+  initial: [u8; 16],
+  width: usize,
+  endian: Endian,
+  position: u64,
+  keystream: Option<(u64, Vec<u8>)>
+}
+
+impl AesCtr {
+  pub fn new(key: &[u8], initial: &[u8], width: usize, endian: Endian) -> AesCtr {
+    assert!(key.len() == 16 && initial.len() == 16);
+    assert!(width > 0 && width <= 16);
+
+    let mut block = [0u8; 16];
+    for (dst, src) in block.iter_mut().zip(initial.iter()) {
+      *dst = *src;
+    }
+
+    AesCtr {
+      key: key.to_vec(),
+      initial: block,
+      width: width,
+      endian: endian,
+      position: 0,
+      keystream: None
+    }
+  }
+
+  // The cryptopals format: 64-bit little-endian nonce, followed by a 64-bit
+  // little-endian block counter.
+  pub fn cryptopals(key: &[u8], nonce: u64) -> AesCtr {
+    let mut initial = [0u8; 16];
+    for i in 0..8 {
+      initial[i] = (nonce >> (i * 8)) as u8;
+    }
+
+    AesCtr::new(key, &initial, 8, Endian::Little)
+  }
+
+  pub fn position(&self) -> u64 {
+    self.position
+  }
+
+  pub fn seek(&mut self, position: u64) {
+    self.position = position;
+  }
+
+  // Transforms the next |data.len()| bytes of the stream.
+  pub fn update(&mut self, data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+
+    for byte in data {
+      let index = self.position / 16;
+
+      // Only encrypt a new counter block when we cross a block boundary.
+      let stale = match self.keystream {
+        Some((cached, _)) => cached != index,
+        None => true
+      };
+
+      if stale {
+        let block = aes_128_ecb_encrypt(&self.key, &self.counter_block(index));
+        self.keystream = Some((index, block));
+      }
+
+      if let Some((_, ref keystream)) = self.keystream {
+        output.push(byte ^ keystream[(self.position % 16) as usize]);
+      }
+
+      self.position += 1;
+    }
+
+    output
+  }
+
+  // Adds |index| to the initial counter, byte by byte, starting with the
+  // least significant one. Carries beyond the counter field are dropped.
+  fn counter_block(&self, index: u64) -> [u8; 16] {
+    let mut block = self.initial;
+    let mut carry = index;
+
+    for n in 0..self.width {
+      if carry == 0 {
+        break;
+      }
+
+      let pos = match self.endian {
+        Endian::Big => 15 - n,
+        Endian::Little => 16 - self.width + n
+      };
 
-  let keystream = (0u64..num_blocks).flat_map(|counter| {
-    iv[1] = counter.to_le();
-    aes_128_ecb_encrypt(key, buf).into_iter()
-  });
+      let sum = block[pos] as u64 + (carry & 0xff);
+      block[pos] = sum as u8;
+      carry = (carry >> 8) + (sum >> 8);
+    }
 
-  Vec::from_iter(keystream).xor(plaintext)
+    block
+  }
+}
+
+pub fn aes_128_ctr_transform(key: &[u8], nonce: u64, plaintext: &[u8]) -> Vec<u8> {
+  AesCtr::cryptopals(key, nonce).update(plaintext)
 }
 
 #[cfg(test)]
 mod test {
+  use challenge1::HexDecoder;
   use challenge6::Base64Decoder;
   use aes_128_ctr_transform;
+  use AesCtr;
+  use Endian;
 
   #[test]
   fn test() {
@@ -34,4 +135,72 @@ mod test {
     let decrypted = aes_128_ctr_transform(key, nonce, &data);
     assert_eq!(String::from_utf8(decrypted).unwrap(), "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby ");
   }
+
+  #[test]
+  fn test_nist() {
+    // NIST SP 800-38A, F.5.1 and F.5.2 (CTR-AES128).
+    let key = "2b7e151628aed2a6abf7158809cf4f3c".from_hex();
+    let counter = "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff".from_hex();
+    let plaintext = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                     30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710".from_hex();
+    let ciphertext = "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+                      5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee".from_hex();
+
+    let mut ctr = AesCtr::new(&key, &counter, 16, Endian::Big);
+    assert_eq!(ctr.update(&plaintext), ciphertext);
+
+    let mut ctr = AesCtr::new(&key, &counter, 16, Endian::Big);
+    assert_eq!(ctr.update(&ciphertext), plaintext);
+  }
+
+  #[test]
+  fn test_rfc3686() {
+    // RFC 3686, test vector #1: 32-bit nonce, 64-bit IV, 32-bit BE counter.
+    let key = "ae6852f8121067cc4bf7a5765577f39e".from_hex();
+    let counter = "00000030000000000000000000000001".from_hex();
+    let ciphertext = "e4095d4fb7a7b3792d6175a3261311b8".from_hex();
+
+    let mut ctr = AesCtr::new(&key, &counter, 4, Endian::Big);
+    assert_eq!(ctr.update(b"Single block msg"), ciphertext);
+  }
+
+  #[test]
+  fn test_seek_and_stream() {
+    let key = b"YELLOW SUBMARINE";
+    let plaintext: Vec<u8> = (0..200).map(|i| i as u8).collect();
+    let ciphertext = aes_128_ctr_transform(key, 42, &plaintext);
+
+    // Streaming in odd-sized chunks yields the same result.
+    let mut ctr = AesCtr::cryptopals(key, 42);
+    let mut streamed = Vec::new();
+    for chunk in plaintext.chunks(7) {
+      streamed.extend(ctr.update(chunk));
+    }
+    assert_eq!(streamed, ciphertext);
+    assert_eq!(ctr.position(), 200);
+
+    // Seeking gives random access to the keystream.
+    for &offset in [0usize, 15, 16, 17, 100, 199].iter() {
+      ctr.seek(offset as u64);
+      assert_eq!(ctr.update(&plaintext[offset..]), &ciphertext[offset..]);
+    }
+  }
+
+  #[test]
+  fn test_counter_wraps() {
+    let key = b"YELLOW SUBMARINE";
+    let data = [0u8; 32];
+
+    // A 32-bit counter wraps to zero without touching the nonce.
+    let mut ctr = AesCtr::new(key, &[0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+                                     0xaa, 0xaa, 0xaa, 0xaa, 0xff, 0xff, 0xff, 0xff], 4, Endian::Big);
+    let mut wrapped = AesCtr::new(key, &[0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+                                         0xaa, 0xaa, 0xaa, 0xaa, 0, 0, 0, 0], 4, Endian::Big);
+    assert_eq!(&ctr.update(&data)[16..], &wrapped.update(&data[..16])[..]);
+
+    // Little-endian counters carry towards the end of the block.
+    let mut ctr = AesCtr::new(key, &[0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0, 0, 0, 0, 0, 0, 0], 8, Endian::Little);
+    let mut carried = AesCtr::new(key, &[0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0], 8, Endian::Little);
+    assert_eq!(&ctr.update(&data)[16..], &carried.update(&data[..16])[..]);
+  }
 }
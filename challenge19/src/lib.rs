@@ -1,10 +1,14 @@
 extern crate rand;
 extern crate challenge2;
+extern crate challenge4;
 extern crate challenge6;
 extern crate challenge18;
 
 use rand::{Rng, OsRng};
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::iter::{FromIterator, repeat};
+use challenge4::read_file;
 use challenge2::Xor;
 use challenge6::Base64Decoder;
 use challenge18::aes_128_ctr_transform;
@@ -108,10 +112,178 @@ pub fn recover_plaintexts(cts: &[Vec<u8>]) -> Vec<Vec<u8>> {
   Vec::from_iter(cts.iter().map(|ct| ct.xor(&key)))
 }
 
+// The longest context a language model can use.
+const MAX_ORDER: usize = 8;
+
+struct Counts {
+  total: u32,
+  next: HashMap<u8, u32>
+}
+
+// A character n-gram model with Witten-Bell smoothing. Every context of
+// length |order - 1| down to zero gets its own counts, and unseen n-grams
+// back off to shorter contexts and finally to a uniform distribution.
+pub struct LanguageModel {
+  order: usize,
+  contexts: HashMap<Vec<u8>, Counts>
+}
+
+impl LanguageModel {
+  pub fn train(corpus: &[u8], order: usize) -> LanguageModel {
+    assert!(order > 0 && order <= MAX_ORDER);
+    let mut contexts = HashMap::new();
+
+    // Every line starts with a newline as its context.
+    let mut text = vec!(b'\n');
+    text.extend(corpus.iter().cloned());
+
+    for i in 1..text.len() {
+      for len in 0..order {
+        if len > i {
+          break;
+        }
+
+        let counts = contexts.entry(text[i - len..i].to_vec()).or_insert(Counts {
+          total: 0,
+          next: HashMap::new()
+        });
+
+        counts.total += 1;
+        *counts.next.entry(text[i]).or_insert(0) += 1;
+      }
+    }
+
+    LanguageModel { order: order, contexts: contexts }
+  }
+
+  pub fn from_file(path: &str, order: usize) -> LanguageModel {
+    LanguageModel::train(read_file(path).as_bytes(), order)
+  }
+
+  pub fn order(&self) -> usize {
+    self.order
+  }
+
+  // Whether |chr| appeared anywhere in the training corpus.
+  pub fn knows(&self, chr: u8) -> bool {
+    self.contexts.get(&[][..]).map_or(false, |counts| counts.next.contains_key(&chr))
+  }
+
+  // The log probability of |chr| following |context|. Only the last
+  // |order - 1| bytes of |context| are taken into account.
+  pub fn log_prob(&self, context: &[u8], chr: u8) -> f64 {
+    let start = context.len().saturating_sub(self.order - 1);
+    self.prob(&context[start..], chr).ln()
+  }
+
+  fn prob(&self, context: &[u8], chr: u8) -> f64 {
+    let lower = if context.is_empty() {
+      1f64 / 256f64
+    } else {
+      self.prob(&context[1..], chr)
+    };
+
+    match self.contexts.get(context) {
+      Some(counts) => {
+        let seen = *counts.next.get(&chr).unwrap_or(&0) as f64;
+        let types = counts.next.len() as f64;
+        (seen + types * lower) / (counts.total as f64 + types)
+      }
+      None => lower
+    }
+  }
+}
+
+struct Hypothesis {
+  key: Vec<u8>,
+  score: f64
+}
+
+// Jointly decodes ciphertexts encrypted with the same keystream, position
+// by position. Each hypothesis is a keystream prefix, scored by the sum of
+// the language model log probabilities of all plaintexts it yields. Only
+// the |beam_width| best hypotheses survive each step.
+pub fn solve_many_time_pad(model: &LanguageModel, cts: &[Vec<u8>], beam_width: usize) -> Vec<u8> {
+  let max_len = cts.iter().map(|ct| ct.len()).max().unwrap_or(0);
+  let mut beam = vec!(Hypothesis { key: vec!(), score: 0f64 });
+  let mut context = Vec::with_capacity(MAX_ORDER);
+
+  for pos in 0..max_len {
+    let covering = Vec::from_iter(cts.iter().filter(|ct| pos < ct.len()));
+
+    // Only consider key bytes that yield known characters for all lines.
+    let candidates = Vec::from_iter((0..256).map(|k| k as u8).filter(|k| {
+      covering.iter().all(|ct| model.knows(ct[pos] ^ *k))
+    }));
+
+    // Fall back to all key bytes if that leaves nothing.
+    let candidates = if candidates.is_empty() {
+      Vec::from_iter((0..256).map(|k| k as u8))
+    } else {
+      candidates
+    };
+
+    let mut next = Vec::with_capacity(beam.len() * candidates.len());
+
+    for hyp in beam.iter() {
+      for k in candidates.iter() {
+        let mut score = hyp.score;
+
+        for ct in covering.iter() {
+          // Decrypt the preceding |order - 1| bytes of this line.
+          let start = pos.saturating_sub(model.order() - 1);
+          context.clear();
+          if start == 0 {
+            context.push(b'\n');
+          }
+          context.extend((start..pos).map(|i| ct[i] ^ hyp.key[i]));
+
+          score += model.log_prob(&context, ct[pos] ^ *k);
+        }
+
+        next.push((score, hyp, *k));
+      }
+    }
+
+    // Keep the best |beam_width| hypotheses.
+    next.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+    next.truncate(beam_width);
+
+    beam = Vec::from_iter(next.into_iter().map(|(score, hyp, k)| {
+      let mut key = hyp.key.clone();
+      key.push(k);
+      Hypothesis { key: key, score: score }
+    }));
+  }
+
+  beam.swap_remove(0).key
+}
+
+// A two-time pad only gives us |xor| = P_1 ^ P_2. That's a many-time pad
+// with C_1 = |xor| and C_2 = 0, where the keystream is P_2 itself.
+pub fn solve_two_time_pad(model: &LanguageModel, xor: &[u8], beam_width: usize) -> (Vec<u8>, Vec<u8>) {
+  let zeros = Vec::from_iter(repeat(0u8).take(xor.len()));
+  let second = solve_many_time_pad(model, &[xor.to_vec(), zeros], beam_width);
+  (xor.xor(&second), second)
+}
+
 #[cfg(test)]
 mod test {
+  use challenge2::Xor;
+  use challenge6::Base64Decoder;
+  use std::iter::FromIterator;
   use BlackBox;
+  use LanguageModel;
+  use SECRETS;
   use recover_plaintexts;
+  use solve_many_time_pad;
+  use solve_two_time_pad;
+
+  fn count_correct(plaintexts: &[Vec<u8>]) -> usize {
+    SECRETS.iter().zip(plaintexts.iter()).fold(0, |sum, (secret, plaintext)| {
+      sum + secret.from_base64().iter().zip(plaintext.iter()).filter(|&(a, b)| a == b).count()
+    })
+  }
 
   #[test]
   fn test() {
@@ -131,4 +303,43 @@ mod test {
     let expected = b" terrible beauty is born";
     assert_eq!(&plaintexts[39][1..expected.len()+1], expected);
   }
+
+  #[test]
+  fn test_language_model() {
+    let model = LanguageModel::train(b"the cat sat on the mat", 3);
+    assert!(model.knows(b'c') && !model.knows(b'z'));
+
+    // Seen continuations are more likely than unseen ones.
+    assert!(model.log_prob(b"th", b'e') > model.log_prob(b"th", b'a'));
+    assert!(model.log_prob(b"xyz th", b'e') == model.log_prob(b"th", b'e'));
+  }
+
+  #[test]
+  fn test_many_time_pad() {
+    let model = LanguageModel::from_file("corpus.txt", 5);
+    let blackbox = BlackBox::new();
+    let ciphertexts = blackbox.encrypt();
+
+    let key = solve_many_time_pad(&model, &ciphertexts, 32);
+    let plaintexts = Vec::from_iter(ciphertexts.iter().map(|ct| ct.xor(&key)));
+
+    assert_eq!(&plaintexts[0][..], b"I have met them at close of day");
+    assert!(count_correct(&plaintexts) > count_correct(&recover_plaintexts(&ciphertexts)));
+  }
+
+  #[test]
+  fn test_two_time_pad() {
+    let model = LanguageModel::from_file("corpus.txt", 5);
+    let first = b"Send more money to the usual account, please";
+    let second = b"We will arrive early on Sunday and stay there";
+
+    let (p1, p2) = solve_two_time_pad(&model, &first.xor(second), 512);
+
+    // The solver can't know which plaintext continues which, so the two
+    // might cross over. Check that each position yields the right pair.
+    let correct = (0..first.len()).filter(|&i| {
+      (p1[i] == first[i] && p2[i] == second[i]) || (p1[i] == second[i] && p2[i] == first[i])
+    }).count();
+    assert!(correct * 10 >= first.len() * 9);
+  }
 }
@@ -1,9 +1,14 @@
 extern crate rand;
+extern crate challenge2;
 extern crate challenge6;
 extern crate challenge18;
 
+#[cfg(test)]
+extern crate challenge19;
+
 use rand::{Rng, OsRng};
-use std::iter::FromIterator;
+use std::iter::{FromIterator, repeat};
+use challenge2::Xor;
 use challenge6::{Base64Decoder, find_repeated_xor_decryption_for_keysize};
 use challenge18::aes_128_ctr_transform;
 
@@ -93,9 +98,188 @@ pub fn recover_plaintexts(cts: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
   }))
 }
 
+// Breaks a fixed-nonce CTR encryption column by column, using every
+// ciphertext that covers a given position. Each of the 256 candidate key
+// bytes is scored by the log-likelihood of the resulting plaintext bytes,
+// so columns covered by many samples get sharper, more confident results.
+pub struct Breaker {
+  cts: Vec<Vec<u8>>,
+  key: Vec<u8>,
+  confidence: Vec<f64>,
+  locked: Vec<bool>
+}
+
+impl Breaker {
+  pub fn new(cts: &[Vec<u8>]) -> Breaker {
+    let max_len = cts.iter().map(|ct| ct.len()).max().unwrap_or(0);
+
+    let mut breaker = Breaker {
+      cts: cts.to_vec(),
+      key: Vec::from_iter(repeat(0u8).take(max_len)),
+      confidence: Vec::from_iter(repeat(0f64).take(max_len)),
+      locked: Vec::from_iter(repeat(false).take(max_len))
+    };
+
+    breaker.solve();
+    breaker
+  }
+
+  pub fn keystream(&self) -> &[u8] {
+    &self.key
+  }
+
+  // The probability of the chosen key byte at each position, relative to
+  // all other candidates. Locked positions have a confidence of 1.
+  pub fn confidence(&self) -> &[f64] {
+    &self.confidence
+  }
+
+  // The number of ciphertexts covering position |pos|.
+  pub fn coverage(&self, pos: usize) -> usize {
+    self.cts.iter().filter(|ct| pos < ct.len()).count()
+  }
+
+  pub fn plaintexts(&self) -> Vec<Vec<u8>> {
+    Vec::from_iter(self.cts.iter().map(|ct| ct.xor(&self.key)))
+  }
+
+  // Fixes the plaintext of ciphertext |index| at |offset| to |text|. This
+  // determines and locks the key bytes at these positions.
+  pub fn lock(&mut self, index: usize, offset: usize, text: &[u8]) {
+    assert!(offset + text.len() <= self.cts[index].len());
+
+    for (i, chr) in text.iter().enumerate() {
+      let pos = offset + i;
+      self.key[pos] = self.cts[index][pos] ^ *chr;
+      self.confidence[pos] = 1f64;
+      self.locked[pos] = true;
+    }
+  }
+
+  pub fn unlock(&mut self, pos: usize) {
+    self.locked[pos] = false;
+  }
+
+  // (Re-)solves all columns that aren't locked. The first pass only looks
+  // at the preceding column, the second one also at the following column.
+  pub fn solve(&mut self) {
+    for pass in 0..2 {
+      for pos in 0..self.key.len() {
+        if !self.locked[pos] {
+          let use_next = pass > 0 || (pos + 1 < self.key.len() && self.locked[pos + 1]);
+          self.solve_column(pos, use_next);
+        }
+      }
+    }
+  }
+
+  fn solve_column(&mut self, pos: usize, use_next: bool) {
+    let mut scores = Vec::with_capacity(256);
+
+    for candidate in 0..256 {
+      let mut score = 0f64;
+
+      for ct in self.cts.iter().filter(|ct| pos < ct.len()) {
+        let chr = ct[pos] ^ candidate as u8;
+        score += char_log_prob(chr);
+
+        score += if pos > 0 {
+          transition_log_prob(char_class(ct[pos - 1] ^ self.key[pos - 1]), chr)
+        } else {
+          transition_log_prob(CharClass::Start, chr)
+        };
+
+        if use_next && pos + 1 < ct.len() {
+          score += transition_log_prob(char_class(chr), ct[pos + 1] ^ self.key[pos + 1]);
+        }
+      }
+
+      scores.push(score);
+    }
+
+    let (best, max) = scores.iter().enumerate().fold((0, scores[0]), |(best, max), (i, score)| {
+      if *score > max { (i, *score) } else { (best, max) }
+    });
+
+    // Normalize, i.e. compute the probability of |best| among all candidates.
+    let sum = scores.iter().fold(0f64, |sum, score| sum + (score - max).exp());
+
+    self.key[pos] = best as u8;
+    self.confidence[pos] = 1f64 / sum;
+  }
+}
+
+#[derive(Clone, Copy)]
+enum CharClass {
+  Lower,
+  Upper,
+  Space,
+  Punct,
+  Digit,
+  Other,
+  Start
+}
+
+fn char_class(chr: u8) -> CharClass {
+  match chr {
+    b'a'...b'z' => CharClass::Lower,
+    b'A'...b'Z' => CharClass::Upper,
+    b' ' => CharClass::Space,
+    b'0'...b'9' => CharClass::Digit,
+    b',' | b'.' | b';' | b':' | b'\'' | b'"' | b'-' | b'!' | b'?' => CharClass::Punct,
+    _ => CharClass::Other
+  }
+}
+
+// English letter frequencies, per mille.
+static LETTER_FREQS: [f64; 26] = [
+  65.0, 12.0, 22.0, 34.0, 102.0, 18.0, 16.0, 50.0, 57.0, 1.0, 6.0, 33.0, 20.0,
+  57.0, 62.0, 15.0, 1.0, 49.0, 53.0, 75.0, 23.0, 8.0, 19.0, 1.5, 16.0, 0.7
+];
+
+fn char_log_prob(chr: u8) -> f64 {
+  let freq = match chr {
+    b'a'...b'z' => LETTER_FREQS[(chr - b'a') as usize],
+    b'A'...b'Z' => LETTER_FREQS[(chr - b'A') as usize] * 0.3,
+    b' ' => 180.0,
+    b',' => 10.0,
+    b'.' => 8.0,
+    b'\'' | b'"' => 2.5,
+    b'-' => 2.0,
+    b';' | b':' | b'!' | b'?' => 1.0,
+    b'0'...b'9' => 0.5,
+    b'\n' => 0.5,
+    33...126 => 0.05,
+    _ => 0.0001
+  };
+
+  (freq / 1000.0).ln()
+}
+
+// How likely a character class follows another one. The last row is for
+// the beginning of a line.
+static TRANSITIONS: [[f64; 6]; 7] = [
+  // Lower, Upper, Space, Punct, Digit, Other
+  [0.75, 0.005, 0.2, 0.04, 0.002, 0.003],  // Lower
+  [0.7, 0.15, 0.08, 0.05, 0.01, 0.01],     // Upper
+  [0.75, 0.18, 0.005, 0.02, 0.04, 0.005],  // Space
+  [0.15, 0.02, 0.75, 0.05, 0.01, 0.02],    // Punct
+  [0.05, 0.01, 0.3, 0.1, 0.5, 0.04],       // Digit
+  [0.17, 0.17, 0.17, 0.17, 0.16, 0.16],    // Other
+  [0.03, 0.9, 0.01, 0.03, 0.02, 0.01]      // Start
+];
+
+fn transition_log_prob(prev: CharClass, next: u8) -> f64 {
+  TRANSITIONS[prev as usize][char_class(next) as usize].ln()
+}
+
 #[cfg(test)]
 mod test {
   use BlackBox;
+  use Breaker;
+  use SECRETS;
+  use challenge6::Base64Decoder;
+  use std::iter::FromIterator;
   use recover_plaintexts;
 
   #[test]
@@ -116,4 +300,52 @@ mod test {
     let expected = b" terrible beauty is";
     assert_eq!(&plaintexts[39][1..expected.len()+1], expected);
   }
+
+  fn count_correct(plaintexts: &[Vec<u8>]) -> usize {
+    SECRETS.iter().zip(plaintexts.iter()).fold(0, |sum, (secret, plaintext)| {
+      sum + secret.from_base64().iter().zip(plaintext.iter()).filter(|&(a, b)| a == b).count()
+    })
+  }
+
+  #[test]
+  fn test_breaker() {
+    let blackbox = BlackBox::new();
+    let ciphertexts = blackbox.encrypt();
+    let breaker = Breaker::new(&ciphertexts);
+    let plaintexts = breaker.plaintexts();
+
+    assert_eq!(&plaintexts[0][..], b"I have met them at close of day");
+    assert_eq!(&plaintexts[39][..], b"A terrible beauty is born.");
+
+    // Beat the approaches of challenges 19 and 20.
+    let correct = count_correct(&plaintexts);
+    assert!(correct > count_correct(&recover_plaintexts(ciphertexts.clone())));
+    assert!(correct > count_correct(&challenge19::recover_plaintexts(&ciphertexts)));
+
+    // Columns covered by all samples should be pretty certain.
+    assert_eq!(breaker.coverage(0), 40);
+    assert!(breaker.confidence()[..20].iter().all(|p| *p > 0.9));
+  }
+
+  #[test]
+  fn test_breaker_lock() {
+    let blackbox = BlackBox::new();
+    let ciphertexts = blackbox.encrypt();
+    let mut breaker = Breaker::new(&ciphertexts);
+
+    // The tail of the longest line is covered by a single ciphertext.
+    let longest = SECRETS[37].from_base64();
+    assert_eq!(breaker.coverage(longest.len() - 1), 1);
+
+    // Lock in corrections and re-solve.
+    breaker.lock(37, 29, b"his ");
+    breaker.solve();
+    assert_eq!(&breaker.plaintexts()[37][..33], &longest[..33]);
+    assert!(breaker.confidence()[29..33].iter().all(|p| *p == 1f64));
+
+    breaker.lock(37, 33, b"turn,");
+    breaker.solve();
+    let expected = Vec::from_iter(SECRETS.iter().map(|secret| secret.from_base64()));
+    assert_eq!(breaker.plaintexts(), expected);
+  }
 }
@@ -2,13 +2,20 @@ extern crate rand;
 extern crate challenge4;
 
 use rand::{Rng, SeedableRng};
+use std::cmp::max;
 
-const N: usize = 624;
-const M: usize = 397;
-const MATRIX_A: u32 = 0x9908b0df;   /* constant vector a */
+pub const N: usize = 624;
+pub const M: usize = 397;
+pub const MATRIX_A: u32 = 0x9908b0df; /* constant vector a */
 const UPPER_MASK: u32 = 0x80000000; /* most significant w-r bits */
 const LOWER_MASK: u32 = 0x7fffffff; /* least significant r bits */
 
+const NN: usize = 312;
+const MM: usize = 156;
+const MATRIX_A_64: u64 = 0xb5026f5aa96619e9;
+const UPPER_MASK_64: u64 = 0xffffffff80000000; /* most significant 33 bits */
+const LOWER_MASK_64: u64 = 0x7fffffff;         /* least significant 31 bits */
+
 pub struct MT19937RNG {
   state: [u32; N],
   index: usize
@@ -19,16 +26,143 @@ impl MT19937RNG {
     MT19937RNG { index: 0, state: state }
   }
 
-  fn generate(&mut self) {
-    for i in 0..N {
-      let byte = (self.state[i] & UPPER_MASK) | (self.state[(i + 1) % N] & LOWER_MASK);
-      self.state[i] = self.state[(i + M) % N] ^ (byte >> 1);
+  pub fn from_array(key: &[u32]) -> Self {
+    let mut rng = MT19937RNG { index: 0, state: [0; N] };
+    rng.init_by_array(key);
+    rng
+  }
+
+  // Seeds with an array of arbitrary length, like mt19937ar.c does.
+  // Python's random module seeds this way too.
+  pub fn init_by_array(&mut self, key: &[u32]) {
+    assert!(key.len() > 0);
+    self.reseed(19650218);
+
+    let mut i = 1;
+    let mut j = 0;
+
+    for _ in 0..max(N, key.len()) {
+      let prev = self.state[i - 1] ^ (self.state[i - 1] >> 30);
+      self.state[i] = (self.state[i] ^ prev.wrapping_mul(1664525))
+        .wrapping_add(key[j]).wrapping_add(j as u32);
+
+      i += 1;
+      j += 1;
+
+      if i >= N {
+        self.state[0] = self.state[N - 1];
+        i = 1;
+      }
+
+      if j >= key.len() {
+        j = 0;
+      }
+    }
+
+    for _ in 0..N - 1 {
+      let prev = self.state[i - 1] ^ (self.state[i - 1] >> 30);
+      self.state[i] = (self.state[i] ^ prev.wrapping_mul(1566083941)).wrapping_sub(i as u32);
 
-      if byte % 2 == 1 {
-        self.state[i] ^= MATRIX_A;
+      i += 1;
+
+      if i >= N {
+        self.state[0] = self.state[N - 1];
+        i = 1;
+      }
+    }
+
+    // MSB is 1, assuring non-zero initial array.
+    self.state[0] = 0x80000000;
+  }
+
+  // Skips the next |n| outputs without tempering them.
+  pub fn discard(&mut self, n: u64) {
+    let mut n = n;
+
+    // Use up what's left of the current block first.
+    if self.index > 0 {
+      let left = (N - self.index) as u64;
+
+      if n < left {
+        self.index += n as usize;
+        return;
       }
+
+      n -= left;
+      self.index = 0;
+    }
+
+    // Skip whole blocks.
+    for _ in 0..n / N as u64 {
+      self.generate();
+    }
+
+    // Skip into the next block.
+    let rest = (n % N as u64) as usize;
+    if rest > 0 {
+      self.generate();
+      self.index = rest;
     }
   }
+
+  // Skips the next |n| outputs in O(log n) polynomial operations. See
+  // |Jump| for a version that can be reused.
+  pub fn jump(&mut self, n: u64) {
+    Jump::new(n).apply(self);
+  }
+
+  // Regenerates the state in three separate loops, like the reference
+  // implementation, so that every index is in bounds without wrapping.
+  fn generate(&mut self) {
+    for i in 0..N - M {
+      self.state[i] = self.state[i + M] ^ twist(self.state[i], self.state[i + 1]);
+    }
+
+    for i in N - M..N - 1 {
+      self.state[i] = self.state[i + M - N] ^ twist(self.state[i], self.state[i + 1]);
+    }
+
+    self.state[N - 1] = self.state[M - 1] ^ twist(self.state[N - 1], self.state[0]);
+  }
+}
+
+// The untempered output that replaces |word|, before adding the word M
+// positions further.
+pub fn twist(word: u32, next: u32) -> u32 {
+  let word = (word & UPPER_MASK) | (next & LOWER_MASK);
+
+  if word % 2 == 1 {
+    (word >> 1) ^ MATRIX_A
+  } else {
+    word >> 1
+  }
+}
+
+// State word |i| of a freshly seeded generator, given word |i - 1|.
+pub fn seed_word(prev: u32, i: usize) -> u32 {
+  0x6c078965u32.wrapping_mul(prev ^ (prev >> 30)).wrapping_add(i as u32)
+}
+
+pub fn temper(word: u32) -> u32 {
+  let mut word = word;
+  word ^= word >> 11;
+  word ^= (word << 7) & 0x9d2c5680;
+  word ^= (word << 15) & 0xefc60000;
+  word ^ (word >> 18)
+}
+
+// rand's fill_bytes() writes every next_u64() = (a << 32) | b little-endian,
+// so the two outputs of each pair come out swapped. Maps byte |offset| of
+// what fill_bytes() wrote to the offset in the outputs written out as four
+// little-endian bytes each.
+pub fn fill_bytes_offset(offset: usize) -> usize {
+  offset ^ 4
+}
+
+// The number of outputs fill_bytes() uses for |len| bytes. It always
+// consumes pairs, leftover bytes are dropped.
+pub fn fill_bytes_outputs(len: usize) -> usize {
+  (len + 7) / 8 * 2
 }
 
 impl SeedableRng<u32> for MT19937RNG {
@@ -37,8 +171,7 @@ impl SeedableRng<u32> for MT19937RNG {
     self.state[0] = seed;
 
     for i in 1..N {
-      let prev = self.state[i - 1] as u64;
-      self.state[i] = (0x6c078965 * (prev ^ (prev >> 30)) + i as u64) as u32;
+      self.state[i] = seed_word(self.state[i - 1], i);
     }
   }
 
@@ -55,15 +188,369 @@ impl Rng for MT19937RNG {
       self.generate();
     }
 
+    let word = temper(self.state[self.index]);
+    self.index = (self.index + 1) % N;
+    word
+  }
+}
+
+// The exponents of the 135 non-zero terms of phi(x), the characteristic
+// polynomial of the MT19937 transition over GF(2). Its degree 19937 is the
+// number of state bits that actually matter.
+static PHI_TERMS: [usize; 135] = [
+  0, 1189, 1416, 1585, 1643, 1870, 2493, 2773, 3000, 3227, 3454, 3681, 3908,
+  4135, 4362, 4753, 5661, 6337, 6569, 7129, 7477, 7525, 7583, 7752, 7979, 8206,
+  9505, 9901, 9969, 10128, 10693, 10761, 10920, 11089, 11147, 11157, 11215,
+  11321, 11374, 11384, 11485, 11611, 11712, 11717, 11838, 11881, 11944, 11997,
+  12277, 12335, 12393, 12504, 12509, 12620, 12673, 12731, 12736, 12789, 12905,
+  12958, 12963, 13137, 13185, 13190, 13243, 13301, 13412, 13528, 13533, 13639,
+  13697, 13760, 13813, 13866, 14093, 14151, 14209, 14320, 14325, 14436, 14547,
+  14552, 14605, 14721, 14774, 14779, 14953, 15001, 15006, 15059, 15117, 15228,
+  15344, 15349, 15455, 15513, 15576, 15629, 15682, 15909, 15967, 16025, 16136,
+  16141, 16252, 16363, 16368, 16421, 16537, 16590, 16595, 16817, 16822, 16875,
+  16933, 17044, 17160, 17271, 17329, 17445, 17498, 17725, 17783, 17841, 17952,
+  18068, 18179, 18237, 18406, 18633, 18691, 18860, 19087, 19314, 19937
+];
+
+// Jump-ahead using the characteristic polynomial phi(x) of the MT19937
+// transition T over GF(2), as described by Haramoto et al. Advancing by |n|
+// steps means computing T^n, and with g(x) = x^n mod phi(x) we have
+// T^n = g(T) as phi(T) = 0. g(T) is then evaluated using Horner's rule.
+pub struct Jump {
+  poly: Option<Vec<u64>>
+}
+
+impl Jump {
+  pub fn new(n: u64) -> Jump {
+    if n == 0 {
+      return Jump { poly: None };
+    }
+
+    // The seeded state might have garbage in the low bits of the first
+    // word that phi(x) doesn't account for. We'll do one step manually.
+    Jump { poly: Some(poly_pow_x(n - 1, &PHI_TERMS)) }
+  }
+
+  pub fn apply(&self, rng: &mut MT19937RNG) {
+    let poly = match self.poly {
+      Some(ref poly) => poly,
+      None => return
+    };
+
+    // The generator state is a window of N consecutive words. Jumping
+    // the window is the same as jumping the generator, whatever its index.
+    let mut window = Window { words: rng.state, pos: 0 };
+    window.step();
+
+    let mut acc = Window { words: [0; N], pos: 0 };
+    for i in (0..poly_len(poly)).rev() {
+      acc.step();
+
+      if poly_bit(poly, i) {
+        acc.add(&window);
+      }
+    }
+
+    for i in 0..N {
+      rng.state[i] = acc.word(i);
+    }
+  }
+}
+
+// A circular buffer of N consecutive state words, advanced one word at a
+// time, contrary to the block-wise MT19937RNG::generate().
+struct Window {
+  words: [u32; N],
+  pos: usize
+}
+
+impl Window {
+  fn word(&self, i: usize) -> u32 {
+    self.words[(self.pos + i) % N]
+  }
+
+  fn step(&mut self) {
+    let next = self.word(M) ^ twist(self.word(0), self.word(1));
+    self.words[self.pos] = next;
+    self.pos = (self.pos + 1) % N;
+  }
+
+  fn add(&mut self, other: &Window) {
+    for i in 0..N {
+      self.words[(self.pos + i) % N] ^= other.word(i);
+    }
+  }
+}
+
+// Polynomials over GF(2) are stored as bit vectors, with bit |i| being the
+// coefficient of x^i.
+fn poly_bit(poly: &[u64], i: usize) -> bool {
+  i / 64 < poly.len() && (poly[i / 64] >> (i % 64)) & 1 == 1
+}
+
+// The number of coefficients, i.e. the degree + 1.
+fn poly_len(poly: &[u64]) -> usize {
+  for i in (0..poly.len()).rev() {
+    if poly[i] != 0 {
+      return i * 64 + 64 - poly[i].leading_zeros() as usize;
+    }
+  }
+
+  0
+}
+
+// Adds (xors) |src| * x^|shift| to |dst|.
+fn poly_add_shifted(dst: &mut Vec<u64>, src: &[u64], shift: usize) {
+  let words = shift / 64;
+  let bits = shift % 64;
+
+  if dst.len() < src.len() + words + 1 {
+    dst.resize(src.len() + words + 1, 0);
+  }
+
+  for (i, word) in src.iter().enumerate() {
+    dst[i + words] ^= word << bits;
+
+    if bits > 0 {
+      dst[i + words + 1] ^= word >> (64 - bits);
+    }
+  }
+}
+
+// The length |l| of the shortest LFSR that generates |bits|, and its
+// connection polynomial c(x), computed with Berlekamp-Massey, 64 bits at a
+// time.
+pub fn berlekamp_massey(bits: &[bool]) -> (usize, Vec<u64>) {
+  let len = bits.len();
+
+  // Store the sequence in reverse, so that the discrepancy below can be
+  // computed a word at a time.
+  let mut seq = vec!(0u64; len / 64 + 2);
+  for (i, bit) in bits.iter().enumerate() {
+    if *bit {
+      let j = len - 1 - i;
+      seq[j / 64] |= 1 << (j % 64);
+    }
+  }
+
+  // The 64 sequence bits starting at |offset|.
+  let seq_word = |offset: usize| -> u64 {
+    let (i, bits) = (offset / 64, offset % 64);
+    if bits == 0 {
+      seq[i]
+    } else {
+      (seq[i] >> bits) | (seq[i + 1] << (64 - bits))
+    }
+  };
+
+  let mut c = vec!(1u64);
+  let mut b = vec!(1u64);
+  let mut l = 0;
+  let mut m = 1;
+
+  for n in 0..len {
+    // d = sum of c_i * s_(n - i) for i = 0..l
+    let offset = len - 1 - n;
+    let parity = (0..l / 64 + 1).fold(0, |acc, i| {
+      acc ^ (c.get(i).map_or(0, |word| word & seq_word(offset + i * 64))).count_ones()
+    });
+
+    if parity % 2 == 0 {
+      m += 1;
+    } else if 2 * l <= n {
+      let prev = c.clone();
+      poly_add_shifted(&mut c, &b, m);
+      l = n + 1 - l;
+      b = prev;
+      m = 1;
+    } else {
+      poly_add_shifted(&mut c, &b, m);
+      m += 1;
+    }
+  }
+
+  (l, c)
+}
+
+// The length of the shortest LFSR that generates |bits|.
+pub fn linear_complexity(bits: &[bool]) -> usize {
+  berlekamp_massey(bits).0
+}
+
+// Reduces |poly| modulo a sparse polynomial given by the exponents of its
+// non-zero terms, in ascending order.
+fn poly_mod(poly: &mut Vec<u64>, terms: &[usize]) {
+  let degree = terms[terms.len() - 1];
+
+  for i in (degree..poly_len(poly)).rev() {
+    if poly_bit(poly, i) {
+      for term in terms {
+        let j = i - degree + term;
+        poly[j / 64] ^= 1 << (j % 64);
+      }
+    }
+  }
+
+  poly.truncate(degree / 64 + 1);
+}
+
+fn poly_square(poly: &[u64]) -> Vec<u64> {
+  // Squaring over GF(2) only spreads the bits, as all cross terms cancel.
+  fn spread(word: u32) -> u64 {
+    let mut x = word as u64;
+    x = (x | (x << 16)) & 0x0000ffff0000ffff;
+    x = (x | (x << 8)) & 0x00ff00ff00ff00ff;
+    x = (x | (x << 4)) & 0x0f0f0f0f0f0f0f0f;
+    x = (x | (x << 2)) & 0x3333333333333333;
+    (x | (x << 1)) & 0x5555555555555555
+  }
+
+  let mut square = Vec::with_capacity(poly.len() * 2);
+  for word in poly {
+    square.push(spread(*word as u32));
+    square.push(spread((word >> 32) as u32));
+  }
+
+  square
+}
+
+// Computes x^|n| mod |modulus| by square-and-multiply.
+fn poly_pow_x(n: u64, modulus: &[usize]) -> Vec<u64> {
+  let mut result = vec!(1u64);
+
+  for i in (0..64 - n.leading_zeros()).rev() {
+    result = poly_square(&result);
+    poly_mod(&mut result, modulus);
+
+    if (n >> i) & 1 == 1 {
+      let mut shifted = vec!();
+      poly_add_shifted(&mut shifted, &result, 1);
+      result = shifted;
+      poly_mod(&mut result, modulus);
+    }
+  }
+
+  result
+}
+
+// The 64-bit variant, as in mt19937-64.c.
+pub struct MT19937_64RNG {
+  state: [u64; NN],
+  index: usize
+}
+
+impl MT19937_64RNG {
+  pub fn from_state(state: [u64; NN]) -> Self {
+    MT19937_64RNG { index: 0, state: state }
+  }
+
+  pub fn from_array(key: &[u64]) -> Self {
+    let mut rng = MT19937_64RNG { index: 0, state: [0; NN] };
+    rng.init_by_array(key);
+    rng
+  }
+
+  pub fn init_by_array(&mut self, key: &[u64]) {
+    assert!(key.len() > 0);
+    self.reseed(19650218);
+
+    let mut i = 1;
+    let mut j = 0;
+
+    for _ in 0..max(NN, key.len()) {
+      let prev = self.state[i - 1] ^ (self.state[i - 1] >> 62);
+      self.state[i] = (self.state[i] ^ prev.wrapping_mul(3935559000370003845))
+        .wrapping_add(key[j]).wrapping_add(j as u64);
+
+      i += 1;
+      j += 1;
+
+      if i >= NN {
+        self.state[0] = self.state[NN - 1];
+        i = 1;
+      }
+
+      if j >= key.len() {
+        j = 0;
+      }
+    }
+
+    for _ in 0..NN - 1 {
+      let prev = self.state[i - 1] ^ (self.state[i - 1] >> 62);
+      self.state[i] = (self.state[i] ^ prev.wrapping_mul(2862933555777941757)).wrapping_sub(i as u64);
+
+      i += 1;
+
+      if i >= NN {
+        self.state[0] = self.state[NN - 1];
+        i = 1;
+      }
+    }
+
+    // MSB is 1, assuring non-zero initial array.
+    self.state[0] = 1 << 63;
+  }
+
+  fn generate(&mut self) {
+    for i in 0..NN - MM {
+      self.state[i] = self.state[i + MM] ^ twist_64(self.state[i], self.state[i + 1]);
+    }
+
+    for i in NN - MM..NN - 1 {
+      self.state[i] = self.state[i + MM - NN] ^ twist_64(self.state[i], self.state[i + 1]);
+    }
+
+    self.state[NN - 1] = self.state[MM - 1] ^ twist_64(self.state[NN - 1], self.state[0]);
+  }
+}
+
+fn twist_64(word: u64, next: u64) -> u64 {
+  let word = (word & UPPER_MASK_64) | (next & LOWER_MASK_64);
+
+  if word % 2 == 1 {
+    (word >> 1) ^ MATRIX_A_64
+  } else {
+    word >> 1
+  }
+}
+
+impl SeedableRng<u64> for MT19937_64RNG {
+  fn reseed(&mut self, seed: u64) {
+    self.index = 0;
+    self.state[0] = seed;
+
+    for i in 1..NN {
+      let prev = self.state[i - 1];
+      self.state[i] = 6364136223846793005u64.wrapping_mul(prev ^ (prev >> 62)).wrapping_add(i as u64);
+    }
+  }
+
+  fn from_seed(seed: u64) -> Self {
+    let mut rng = MT19937_64RNG { index: 0, state: [0; NN] };
+    rng.reseed(seed);
+    rng
+  }
+}
+
+impl Rng for MT19937_64RNG {
+  fn next_u32(&mut self) -> u32 {
+    (self.next_u64() >> 32) as u32
+  }
+
+  fn next_u64(&mut self) -> u64 {
+    if self.index == 0 {
+      self.generate();
+    }
+
     let mut word = self.state[self.index];
 
     // Temper.
-    word ^= word >> 11;
-    word ^= (word << 7) & 0x9d2c5680;
-    word ^= (word << 15) & 0xefc60000;
-    word ^= word >> 18;
+    word ^= (word >> 29) & 0x5555555555555555;
+    word ^= (word << 17) & 0x71d67fffeda60000;
+    word ^= (word << 37) & 0xfff7eee000000000;
+    word ^= word >> 43;
 
-    self.index = (self.index + 1) % N;
+    self.index = (self.index + 1) % NN;
     word
   }
 }
@@ -71,8 +558,13 @@ impl Rng for MT19937RNG {
 #[cfg(test)]
 mod test {
   use rand::{Rng, SeedableRng};
+  use std::iter::FromIterator;
   use challenge4::read_file;
+  use {berlekamp_massey, poly_bit, Jump, Window, N, PHI_TERMS};
   use MT19937RNG;
+  use MT19937_64RNG;
+  use fill_bytes_offset;
+  use fill_bytes_outputs;
 
   #[test]
   fn test() {
@@ -139,4 +631,155 @@ mod test {
       assert_eq!(a, b);
     }
   }
+
+  #[test]
+  fn test_init_by_array() {
+    // mt19937ar.out, init_by_array({0x123, 0x234, 0x345, 0x456}).
+    let data = read_file("array.txt");
+    let numbers = data.split('\n').map(|line| u32::from_str_radix(line, 10));
+
+    let mut rng = MT19937RNG::from_array(&[0x123, 0x234, 0x345, 0x456]);
+    for (a, b) in rng.gen_iter::<u32>().take(1000).zip(numbers) {
+      assert_eq!(a, b.unwrap());
+    }
+  }
+
+  #[test]
+  fn test_64() {
+    // mt19937-64.out, init_by_array64({0x12345, 0x23456, 0x34567, 0x45678}).
+    let data = read_file("data64.txt");
+    let numbers = data.split('\n').map(|line| u64::from_str_radix(line, 10));
+
+    let mut rng = MT19937_64RNG::from_array(&[0x12345, 0x23456, 0x34567, 0x45678]);
+    for (a, b) in rng.gen_iter::<u64>().take(1000).zip(numbers) {
+      assert_eq!(a, b.unwrap());
+    }
+  }
+
+  #[test]
+  fn test_64_seed() {
+    // The 10000th output of a default-constructed std::mt19937_64.
+    let mut rng = MT19937_64RNG::from_seed(5489);
+    assert_eq!(rng.gen_iter::<u64>().nth(9999), Some(9981545732273789042));
+  }
+
+  // Lines of "<seed> <index> <output>".
+  fn read_fixtures(path: &str) -> Vec<(u32, u64, u32)> {
+    let data = read_file(path);
+
+    Vec::from_iter(data.lines().map(|line| {
+      let fields = Vec::from_iter(line.split(' ').map(|field| u64::from_str_radix(field, 10).unwrap()));
+      (fields[0] as u32, fields[1], fields[2] as u32)
+    }))
+  }
+
+  #[test]
+  fn test_reference_seeds() {
+    // mt19937ar init_genrand() outputs for various seeds.
+    for (seed, index, output) in read_fixtures("seeds.txt") {
+      let mut rng = MT19937RNG::from_seed(seed);
+      assert_eq!(rng.gen_iter::<u32>().nth(index as usize), Some(output));
+    }
+  }
+
+  #[test]
+  fn test_python() {
+    // random.seed(seed) followed by random.getrandbits(32) calls.
+    for (seed, index, output) in read_fixtures("python.txt") {
+      let mut rng = MT19937RNG::from_array(&[seed]);
+      assert_eq!(rng.gen_iter::<u32>().nth(index as usize), Some(output));
+    }
+  }
+
+  #[test]
+  fn test_discard() {
+    for (seed, index, output) in read_fixtures("seeds.txt") {
+      let mut rng = MT19937RNG::from_seed(seed);
+      rng.discard(index);
+      assert_eq!(rng.next_u32(), output);
+
+      // Discard in two steps, starting mid-block.
+      if index > 0 {
+        let mut rng = MT19937RNG::from_seed(seed);
+        rng.next_u32();
+        rng.discard(index / 2);
+        rng.discard(index - index / 2 - 1);
+        assert_eq!(rng.next_u32(), output);
+      }
+    }
+  }
+
+  #[test]
+  fn test_jump() {
+    for &n in [0, 1, 2, 623, 624, 625, 1000, 100000].iter() {
+      // From a fresh seed, and from the middle of a block.
+      for &offset in [0, 300].iter() {
+        let mut rng1 = MT19937RNG::from_seed(12345678);
+        let mut rng2 = MT19937RNG::from_seed(12345678);
+        rng1.discard(offset);
+        rng2.discard(offset);
+
+        rng1.discard(n);
+        rng2.jump(n);
+
+        for (a, b) in rng1.gen_iter::<u32>().zip(rng2.gen_iter::<u32>()).take(1000) {
+          assert_eq!(a, b);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_jump_far() {
+    let mut rng1 = MT19937RNG::from_seed(5489);
+    let mut rng2 = MT19937RNG::from_seed(5489);
+
+    // Jumps can be reused and compose.
+    let jump = Jump::new(1 << 40);
+    jump.apply(&mut rng1);
+    jump.apply(&mut rng1);
+    rng2.jump(1 << 41);
+
+    for (a, b) in rng1.gen_iter::<u32>().zip(rng2.gen_iter::<u32>()).take(1000) {
+      assert_eq!(a, b);
+    }
+  }
+
+  // Finds phi(x) by running Berlekamp-Massey on the sequence of most
+  // significant bits of the generated words.
+  #[test]
+  fn test_characteristic_polynomial() {
+    let mut window = Window { words: MT19937RNG::from_seed(5489).state, pos: 0 };
+    let bits = Vec::from_iter((0..2 * 19937 + 64).map(|_| {
+      window.step();
+      window.word(N - 1) >> 31 == 1
+    }));
+
+    let (l, c) = berlekamp_massey(&bits);
+    assert_eq!(l, 19937);
+
+    // phi(x) = x^l * c(1/x)
+    let terms = Vec::from_iter((0..l + 1).rev().filter(|i| poly_bit(&c, *i)).map(|i| l - i));
+    assert_eq!(&terms[..], &PHI_TERMS[..]);
+  }
+
+  #[test]
+  fn test_fill_bytes() {
+    let mut rng1 = MT19937RNG::from_seed(5489);
+    let mut rng2 = MT19937RNG::from_seed(5489);
+
+    let mut bytes = [0u8; 7];
+    rng1.fill_bytes(&mut bytes);
+
+    // The outputs written out as four little-endian bytes each.
+    let words = [rng2.next_u32(), rng2.next_u32()];
+    let stream = Vec::from_iter((0..8).map(|i| (words[i / 4] >> ((i % 4) * 8)) as u8));
+
+    for (offset, byte) in bytes.iter().enumerate() {
+      assert_eq!(*byte, stream[fill_bytes_offset(offset)]);
+    }
+
+    assert_eq!(fill_bytes_outputs(bytes.len()), 2);
+    assert_eq!(rng1.next_u32(), rng2.next_u32());
+  }
 }
@@ -1,44 +1,254 @@
+extern crate crossbeam;
 extern crate rand;
 extern crate time;
 extern crate challenge21;
 
 use rand::{Rng, OsRng, SeedableRng};
+use std::cell::Cell;
+use std::cmp::min;
+use std::ops::Range;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::thread::sleep_ms;
-use challenge21::MT19937RNG;
+use challenge21::{MT19937RNG, N, M, seed_word, temper, twist};
 
-pub fn rng_unix_time() -> MT19937RNG {
+// Seeds are handed out to threads in chunks of this size.
+const CHUNK_SIZE: u64 = 1 << 16;
+
+// Where time-seeded code gets the current time from, and how it waits.
+pub trait Clock {
+  // Seconds since the Unix epoch.
+  fn now(&self) -> u32;
+  fn sleep(&self, secs: u32);
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> u32 {
+    time::get_time().sec as u32
+  }
+
+  fn sleep(&self, secs: u32) {
+    sleep_ms(secs * 1000);
+  }
+}
+
+// A clock that only moves when told to. Sleeping returns immediately.
+pub struct SimulatedClock {
+  time: Cell<u32>
+}
+
+impl SimulatedClock {
+  pub fn new(start: u32) -> SimulatedClock {
+    SimulatedClock { time: Cell::new(start) }
+  }
+
+  pub fn advance(&self, secs: u32) {
+    self.time.set(self.time.get() + secs);
+  }
+}
+
+impl Clock for SimulatedClock {
+  fn now(&self) -> u32 {
+    self.time.get()
+  }
+
+  fn sleep(&self, secs: u32) {
+    self.advance(secs);
+  }
+}
+
+pub fn rng_unix_time<C: Clock>(clock: &C) -> MT19937RNG {
   let mut rng = OsRng::new().unwrap();
 
   // Sleep 40-1000s before seeding.
-  sleep_ms(rng.gen_range(40, 1001) * 1000);
+  clock.sleep(rng.gen_range(40, 1001));
 
-  let mt = MT19937RNG::from_seed(unix_time());
+  let mt = MT19937RNG::from_seed(clock.now());
 
   // Sleep 40-1000s after seeding.
-  sleep_ms(rng.gen_range(40, 1001) * 1000);
+  clock.sleep(rng.gen_range(40, 1001));
 
   mt
 }
 
-pub fn unix_time() -> u32 {
-  time::get_time().sec as u32
+// What we know about an output of a freshly seeded generator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Observed {
+  // The full output with the given index.
+  Output(usize, u32),
+  // A byte of the outputs written out as four little-endian bytes each, at
+  // the given offset. See challenge21::fill_bytes_offset() for bytes written
+  // by fill_bytes().
+  Byte(usize, u8),
+  // The result of gen_range(low, high) computed from the output with the
+  // given index. Assumes there were no rejections before.
+  Range(usize, u32, u32, u32)
+}
+
+impl Observed {
+  fn index(&self) -> usize {
+    match *self {
+      Observed::Output(index, _) => index,
+      Observed::Byte(offset, _) => offset / 4,
+      Observed::Range(index, _, _, _) => index
+    }
+  }
+
+  fn matches(&self, output: u32) -> bool {
+    match *self {
+      Observed::Output(_, value) => output == value,
+      Observed::Byte(offset, value) => (output >> ((offset % 4) * 8)) as u8 == value,
+      Observed::Range(_, low, high, value) => low + output % (high - low) == value
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+  // Find every seed matching the observations.
+  All,
+  // Stop as soon as a matching seed is found. Other threads might still
+  // report a seed or two before they notice.
+  First
+}
+
+// The seeds between |before| seconds ago and |after| seconds from now.
+pub fn time_window<C: Clock>(clock: &C, before: u32, after: u32) -> Range<u64> {
+  let now = clock.now() as u64;
+  now.saturating_sub(before as u64)..min(now + after as u64 + 1, 1 << 32)
+}
+
+// Returns the seeds in |seeds| whose generators produce all |observed|
+// outputs, in ascending order. Use 0..1 << 32 for the full seed space.
+pub fn recover_seeds(observed: &[Observed], seeds: Range<u64>, strategy: Strategy, threads: usize) -> Vec<u32> {
+  recover_seeds_with_progress(observed, seeds, strategy, threads, &|_, _| {})
+}
+
+// Like recover_seeds(), but calls |progress| with the number of seeds
+// checked so far and the total number of seeds after every chunk. Calls
+// are serialized, and the number of seeds checked only grows.
+pub fn recover_seeds_with_progress<F>(observed: &[Observed], seeds: Range<u64>, strategy: Strategy, threads: usize, progress: &F) -> Vec<u32>
+    where F: Fn(u64, u64) + Sync
+{
+  assert!(observed.len() > 0 && threads > 0 && seeds.end <= 1 << 32);
+
+  let mut observed = observed.to_vec();
+  observed.sort_by(|a, b| a.index().cmp(&b.index()));
+
+  let total = seeds.end.saturating_sub(seeds.start);
+  let num_chunks = ((total + CHUNK_SIZE - 1) / CHUNK_SIZE) as usize;
+  let next_chunk = AtomicUsize::new(0);
+  let checked = Mutex::new(0);
+  let done = AtomicBool::new(false);
+  let found = Mutex::new(vec!());
+
+  crossbeam::scope(|scope| {
+    for _ in 0..threads {
+      scope.spawn(|| {
+        while !done.load(Ordering::SeqCst) {
+          let chunk = next_chunk.fetch_add(1, Ordering::SeqCst);
+          if chunk >= num_chunks {
+            break;
+          }
+
+          let start = seeds.start + chunk as u64 * CHUNK_SIZE;
+          let end = min(start + CHUNK_SIZE, seeds.end);
+
+          let mut size = 0;
+          for seed in start..end {
+            size += 1;
+
+            if is_seed(seed as u32, &observed) {
+              found.lock().unwrap().push(seed as u32);
+
+              if strategy == Strategy::First {
+                done.store(true, Ordering::SeqCst);
+                break;
+              }
+            }
+          }
+
+          // Report while holding the lock, so that the callback sees the
+          // counts in ascending order and is never called concurrently.
+          let mut checked = checked.lock().unwrap();
+          *checked += size;
+          progress(*checked, total);
+        }
+      });
+    }
+  });
+
+  let mut found = found.lock().unwrap().clone();
+  found.sort();
+  found
+}
+
+// Checks |observed|, sorted by index, against the generator seeded with |seed|.
+fn is_seed(seed: u32, observed: &[Observed]) -> bool {
+  let last = observed[observed.len() - 1].index();
+
+  // The first outputs only depend on the first few state words.
+  if last < N - M {
+    return is_seed_fast(seed, observed, last);
+  }
+
+  let mut rng = MT19937RNG::from_seed(seed);
+  let mut index = 0;
+  let mut output = rng.next_u32();
+
+  observed.iter().all(|obs| {
+    while index < obs.index() {
+      output = rng.next_u32();
+      index += 1;
+    }
+
+    obs.matches(output)
+  })
+}
+
+// Computes only the state words needed for outputs up to |last|, without
+// generating the whole state. Output i depends on words i, i + 1 and i + M.
+fn is_seed_fast(seed: u32, observed: &[Observed], last: usize) -> bool {
+  let mut state = [0u32; N];
+  state[0] = seed;
+
+  for i in 1..last + M + 1 {
+    state[i] = seed_word(state[i - 1], i);
+  }
+
+  observed.iter().all(|obs| {
+    let i = obs.index();
+    obs.matches(temper(state[i + M] ^ twist(state[i], state[i + 1])))
+  })
 }
 
 #[cfg(test)]
 mod test {
   use rand::{Rng, SeedableRng};
   use rng_unix_time;
-  use unix_time;
-  use challenge21::MT19937RNG;
+  use recover_seeds;
+  use recover_seeds_with_progress;
+  use std::sync::Mutex;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use time_window;
+  use Clock;
+  use Observed;
+  use SimulatedClock;
+  use Strategy;
+  use SystemClock;
+  use challenge21::{MT19937RNG, fill_bytes_offset};
 
   #[test]
   fn test() {
-    let start = unix_time();
-    let mut rng = rng_unix_time();
+    let clock = SimulatedClock::new(1436000000);
+    let start = clock.now();
+    let mut rng = rng_unix_time(&clock);
     let out = rng.next_u32();
 
     // Try all possible seeds.
-    for time in start..unix_time() {
+    for time in start..clock.now() {
       let mut mt = MT19937RNG::from_seed(time);
 
       // If the first output is the same we probably found the seed.
@@ -49,4 +259,94 @@ mod test {
 
     assert!(false);
   }
+
+  #[test]
+  fn test_clocks() {
+    let clock = SimulatedClock::new(1000);
+    clock.sleep(40);
+    clock.advance(2);
+    assert_eq!(clock.now(), 1042);
+
+    // The system clock should be somewhere after July 2015.
+    assert!(SystemClock.now() > 1436000000);
+  }
+
+  #[test]
+  fn test_recover_time_window() {
+    let clock = SimulatedClock::new(1436000000);
+    let start = clock.now();
+    let mut rng = rng_unix_time(&clock);
+    let observed = [Observed::Output(0, rng.next_u32()), Observed::Output(1, rng.next_u32())];
+
+    let seeds = recover_seeds(&observed, time_window(&clock, 2000, 0), Strategy::All, 1);
+    assert_eq!(seeds.len(), 1);
+    assert!(seeds[0] >= start && seeds[0] <= clock.now());
+  }
+
+  #[test]
+  fn test_recover_bytes_and_ranges() {
+    let seed = 0x9e3779b9;
+    let mut rng = MT19937RNG::from_seed(seed);
+
+    let mut bytes = [0u8; 3];
+    rng.fill_bytes(&mut bytes);
+    let range = rng.gen_range(1, 7);
+    let last = rng.gen_iter::<u32>().nth(1000).unwrap();
+
+    let observed = [
+      Observed::Byte(fill_bytes_offset(0), bytes[0]),
+      Observed::Byte(fill_bytes_offset(2), bytes[2]),
+      Observed::Range(2, 1, 7, range),
+      Observed::Output(1003, last)
+    ];
+
+    let seeds = (seed as u64 - 50000)..(seed as u64 + 50000);
+    assert_eq!(recover_seeds(&observed, seeds, Strategy::All, 4), vec!(seed));
+  }
+
+  #[test]
+  fn test_recover_strategies() {
+    let seed = 0xfffff000;
+    let mut rng = MT19937RNG::from_seed(seed);
+    let byte = rng.next_u32() as u8;
+
+    // A single byte matches lots of seeds. Threads shouldn't matter.
+    let observed = [Observed::Byte(0, byte)];
+    let seeds = (seed as u64 - 100000)..(1 << 32);
+    let all = recover_seeds(&observed, seeds.clone(), Strategy::All, 1);
+    assert!(all.len() > 100 && all.contains(&seed));
+    assert_eq!(recover_seeds(&observed, seeds.clone(), Strategy::All, 3), all);
+
+    // Early exit finds one of them.
+    let first = recover_seeds(&observed, seeds, Strategy::First, 2);
+    assert!(first.len() >= 1 && first.iter().all(|seed| all.contains(seed)));
+  }
+
+  #[test]
+  fn test_recover_progress() {
+    let mut rng = MT19937RNG::from_seed(123456);
+    let observed = [Observed::Output(0, rng.next_u32())];
+    let calls = AtomicUsize::new(0);
+    let last = Mutex::new(0);
+
+    let seeds = recover_seeds_with_progress(&observed, 0..200000, Strategy::All, 2, &|checked, total| {
+      let mut last = last.lock().unwrap();
+      assert!(checked > *last && checked <= total && total == 200000);
+      *last = checked;
+      calls.fetch_add(1, Ordering::SeqCst);
+    });
+
+    assert_eq!(seeds, vec!(123456));
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+    assert_eq!(*last.lock().unwrap(), 200000);
+
+    // Stopping early only counts the seeds we actually checked.
+    let last = Mutex::new(0);
+    let seeds = recover_seeds_with_progress(&observed, 0..200000, Strategy::First, 1, &|checked, _| {
+      *last.lock().unwrap() = checked;
+    });
+
+    assert_eq!(seeds, vec!(123456));
+    assert_eq!(*last.lock().unwrap(), 123457);
+  }
 }
@@ -2,7 +2,16 @@ extern crate rand;
 extern crate challenge21;
 
 use rand::{Rng, OsRng, SeedableRng};
-use challenge21::MT19937RNG;
+use std::collections::BTreeMap;
+use std::iter::{FromIterator, repeat};
+use challenge21::{MT19937RNG, MT19937_64RNG, N, M, MATRIX_A, fill_bytes_offset, temper};
+
+// The number of state bits, and of u64 words needed to store them.
+const STATE_BITS: usize = N * 32;
+const ROW_WORDS: usize = STATE_BITS / 64;
+
+// Only 19937 state bits matter, the low 31 bits of the first word don't.
+const RANK: usize = 19937;
 
 pub fn rng_random() -> MT19937RNG {
   let mut rng = OsRng::new().unwrap();
@@ -35,11 +44,258 @@ pub fn clone_mt19937_rng(rng: &mut MT19937RNG) -> MT19937RNG {
   MT19937RNG::from_state(state)
 }
 
+pub fn rng_random_64() -> MT19937_64RNG {
+  let mut rng = OsRng::new().unwrap();
+  MT19937_64RNG::from_seed(rng.next_u64())
+}
+
+// Inverts y ^= (y >> shift) & mask.
+fn undo_right_shift(word: u64, shift: u32, mask: u64) -> u64 {
+  let mut result = word;
+
+  // Each round recovers another |shift| bits, starting at the top.
+  for _ in 0..64 / shift {
+    result = word ^ ((result >> shift) & mask);
+  }
+
+  result
+}
+
+// Inverts y ^= (y << shift) & mask.
+fn undo_left_shift(word: u64, shift: u32, mask: u64) -> u64 {
+  let mut result = word;
+
+  // Each round recovers another |shift| bits, starting at the bottom.
+  for _ in 0..64 / shift {
+    result = word ^ ((result << shift) & mask);
+  }
+
+  result
+}
+
+pub fn untemper_64(word: u64) -> u64 {
+  let word = undo_right_shift(word, 43, 0xffffffffffffffff);
+  let word = undo_left_shift(word, 37, 0xfff7eee000000000);
+  let word = undo_left_shift(word, 17, 0x71d67fffeda60000);
+  undo_right_shift(word, 29, 0x5555555555555555)
+}
+
+pub fn clone_mt19937_64_rng(rng: &mut MT19937_64RNG) -> MT19937_64RNG {
+  let mut state = [0; 312];
+
+  for (i, word) in rng.gen_iter::<u64>().take(312).enumerate() {
+    state[i] = untemper_64(word);
+  }
+
+  MT19937_64RNG::from_state(state)
+}
+
+// A single known bit of the output with index |index|, counted from the
+// first output of the generator we want to recover.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Observation {
+  pub index: usize,
+  pub bit: usize,
+  pub value: bool
+}
+
+// The bits of |output| selected by |mask|.
+pub fn observe_output(index: usize, output: u32, mask: u32) -> Vec<Observation> {
+  Vec::from_iter((0..32).filter(|bit| (mask >> bit) & 1 == 1).map(|bit| {
+    Observation { index: index, bit: bit, value: (output >> bit) & 1 == 1 }
+  }))
+}
+
+// The bits of |bytes| as written by MT19937RNG::fill_bytes(), starting with
+// the output with index |index|, which must be even.
+pub fn observe_bytes(index: usize, bytes: &[u8]) -> Vec<Observation> {
+  assert!(index % 2 == 0);
+
+  Vec::from_iter(bytes.iter().enumerate().flat_map(|(i, byte)| {
+    let offset = fill_bytes_offset(i);
+    let shift = (offset % 4) * 8;
+    observe_output(index + offset / 4, (*byte as u32) << shift, 0xff << shift).into_iter()
+  }))
+}
+
+// Every output bit is a linear function over GF(2) of the 19968 bits of the
+// initial state. We track these functions symbolically while stepping the
+// generator, and collect one equation per observed bit. With 19937
+// independent equations, Gaussian elimination yields the state. Returns a
+// generator whose next output is the one with index 0, or None if the
+// observations are insufficient or inconsistent. Observations of outputs
+// after the state is fully determined are ignored.
+pub fn recover_state(observations: &[Observation]) -> Option<MT19937RNG> {
+  let mut by_index = BTreeMap::new();
+  for obs in observations {
+    by_index.entry(obs.index).or_insert(vec!()).push(*obs);
+  }
+
+  // For every bit of a tempered output, the untempered bits it consists of.
+  let temper_rows = Vec::from_iter((0..32).map(|bit| {
+    Vec::from_iter((0..32).filter(|i| (temper(1 << i) >> bit) & 1 == 1))
+  }));
+
+  // Initially, bit |b| of state word |i| is just variable |i * 32 + b|.
+  let mut window = Vec::from_iter(repeat(0u64).take(N * 32 * ROW_WORDS));
+  for var in 0..STATE_BITS {
+    window[var * ROW_WORDS + var / 64] = 1 << (var % 64);
+  }
+
+  let mut system = System::new();
+  let mut index = 0;
+
+  for (next, observations) in by_index {
+    // Step the symbolic generator up to the observed output.
+    while index <= next {
+      step_symbolic(&mut window, index);
+      index += 1;
+    }
+
+    // The untempered output now occupies the slot we just overwrote.
+    let slot = (next % N) * 32;
+
+    for obs in observations {
+      let mut row = Vec::from_iter(repeat(0u64).take(ROW_WORDS));
+      for bit in temper_rows[obs.bit].iter() {
+        xor_row(&mut row, symbolic_bit(&window, slot + bit));
+      }
+
+      if !system.insert(row, obs.value) {
+        return None;
+      }
+    }
+
+    if system.rank == RANK {
+      break;
+    }
+  }
+
+  system.solve().map(|bits| {
+    let mut state = [0; N];
+    for (var, bit) in bits.iter().enumerate() {
+      if *bit {
+        state[var / 32] |= 1 << (var % 32);
+      }
+    }
+
+    MT19937RNG::from_state(state)
+  })
+}
+
+fn symbolic_bit(window: &[u64], bit: usize) -> &[u64] {
+  &window[bit * ROW_WORDS..(bit + 1) * ROW_WORDS]
+}
+
+fn xor_row(dst: &mut [u64], src: &[u64]) {
+  for (a, b) in dst.iter_mut().zip(src.iter()) {
+    *a ^= *b;
+  }
+}
+
+// Computes the untempered output with index |index| and stores it in the
+// slot of the word it replaces, like MT19937RNG::generate() does.
+fn step_symbolic(window: &mut [u64], index: usize) {
+  let slot = |offset: usize| ((index + offset) % N) * 32;
+  let mut word = Vec::from_iter(repeat(0u64).take(32 * ROW_WORDS));
+
+  for bit in 0..32 {
+    let row = &mut word[bit * ROW_WORDS..(bit + 1) * ROW_WORDS];
+    xor_row(row, symbolic_bit(window, slot(M) + bit));
+
+    // y = (x_i & UPPER_MASK) | (x_i+1 & LOWER_MASK), shifted right by one.
+    if bit < 30 {
+      xor_row(row, symbolic_bit(window, slot(1) + bit + 1));
+    } else if bit == 30 {
+      xor_row(row, symbolic_bit(window, slot(0) + 31));
+    }
+
+    // Add MATRIX_A if the lowest bit of y is set.
+    if (MATRIX_A >> bit) & 1 == 1 {
+      xor_row(row, symbolic_bit(window, slot(1)));
+    }
+  }
+
+  let start = slot(0) * ROW_WORDS;
+  for (dst, src) in window[start..start + 32 * ROW_WORDS].iter_mut().zip(word) {
+    *dst = src;
+  }
+}
+
+// A linear system over GF(2) in row echelon form. Each row is stored at the
+// index of its lowest set bit, its pivot.
+struct System {
+  rows: Vec<Option<(Vec<u64>, bool)>>,
+  rank: usize
+}
+
+impl System {
+  fn new() -> System {
+    System { rows: Vec::from_iter((0..STATE_BITS).map(|_| None)), rank: 0 }
+  }
+
+  // Reduces and adds an equation. Returns false if it's inconsistent with
+  // the equations added so far.
+  fn insert(&mut self, row: Vec<u64>, value: bool) -> bool {
+    let mut row = row;
+    let mut value = value;
+
+    loop {
+      let pivot = match row.iter().position(|word| *word != 0) {
+        Some(i) => i * 64 + row[i].trailing_zeros() as usize,
+        None => return !value
+      };
+
+      match self.rows[pivot] {
+        Some((ref other, other_value)) => {
+          // Both rows are zero below the pivot's word.
+          xor_row(&mut row[pivot / 64..], &other[pivot / 64..]);
+          value ^= other_value;
+        }
+        None => {
+          self.rows[pivot] = Some((row, value));
+          self.rank += 1;
+          return true;
+        }
+      }
+    }
+  }
+
+  // Back-substitutes, starting with the highest pivot. Free variables are
+  // set to zero.
+  fn solve(&self) -> Option<Vec<bool>> {
+    if self.rank < RANK {
+      return None;
+    }
+
+    let mut bits = Vec::from_iter(repeat(0u64).take(ROW_WORDS));
+
+    for pivot in (0..STATE_BITS).rev() {
+      if let Some((ref row, value)) = self.rows[pivot] {
+        let parity = row.iter().zip(bits.iter()).fold(0, |acc, (a, b)| acc ^ (a & b).count_ones());
+
+        if (parity % 2 == 1) != value {
+          bits[pivot / 64] |= 1 << (pivot % 64);
+        }
+      }
+    }
+
+    Some(Vec::from_iter((0..STATE_BITS).map(|var| (bits[var / 64] >> (var % 64)) & 1 == 1)))
+  }
+}
+
 #[cfg(test)]
 mod test {
-  use rand::{Rng};
+  use rand::{Rng, SeedableRng};
+  use std::iter::FromIterator;
+  use challenge21::MT19937RNG;
+  use observe_bytes;
+  use observe_output;
+  use recover_state;
   use rng_random;
+  use rng_random_64;
   use clone_mt19937_rng;
+  use clone_mt19937_64_rng;
 
   #[test]
   fn test() {
@@ -53,4 +309,68 @@ mod test {
       assert_eq!(a, b);
     }
   }
+
+  #[test]
+  fn test_64() {
+    let mut rng = rng_random_64();
+    let mut clone = clone_mt19937_64_rng(&mut rng);
+
+    let out1 = rng.gen_iter::<u64>();
+    let out2 = clone.gen_iter::<u64>();
+
+    for (a, b) in out1.zip(out2.take(1000)) {
+      assert_eq!(a, b);
+    }
+  }
+
+  #[test]
+  fn test_recover_fill_bytes() {
+    let mut rng = MT19937RNG::from_seed(0xdeadbeef);
+    let mut observations = vec!();
+
+    // Observe three bytes of every other pair of outputs.
+    for i in 0..2000 {
+      let mut bytes = [0u8; 3];
+      rng.fill_bytes(&mut bytes);
+      observations.extend(observe_bytes(i * 4, &bytes));
+      rng.next_u64();
+    }
+
+    let mut clone = recover_state(&observations).unwrap();
+    clone.discard(8000);
+
+    for (a, b) in rng.gen_iter::<u32>().zip(clone.gen_iter::<u32>()).take(1000) {
+      assert_eq!(a, b);
+    }
+  }
+
+  #[test]
+  fn test_recover_gen_range() {
+    let mut rng = MT19937RNG::from_seed(1234567);
+    let mut observations = vec!();
+
+    // A power-of-two range gives us the low bits. These aren't all
+    // independent, so we need quite a few more than 19937.
+    for i in 0..5000 {
+      let value = rng.gen_range(0, 256);
+      observations.extend(observe_output(i, value, 0xff));
+    }
+
+    let mut clone = recover_state(&observations).unwrap();
+    clone.discard(5000);
+
+    for (a, b) in rng.gen_iter::<u32>().zip(clone.gen_iter::<u32>()).take(1000) {
+      assert_eq!(a, b);
+    }
+  }
+
+  #[test]
+  fn test_recover_insufficient() {
+    let mut rng = MT19937RNG::from_seed(1234567);
+    let observations = Vec::from_iter((0..100).flat_map(|i| {
+      observe_output(i, rng.next_u32(), 0xffffffff).into_iter()
+    }));
+
+    assert!(recover_state(&observations).is_none());
+  }
 }
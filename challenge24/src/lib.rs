@@ -1,22 +1,188 @@
 extern crate rand;
+extern crate challenge1;
 extern crate challenge2;
+extern crate challenge6;
 extern crate challenge21;
 extern crate challenge22;
+extern crate challenge23;
 
 use rand::{Rng, OsRng, SeedableRng};
+use std::cmp::min;
+use std::io::{self, Read, Write};
 use std::iter::FromIterator;
-use challenge2::Xor;
-use challenge21::MT19937RNG;
-use challenge22::unix_time;
+use std::ops::Range;
+use challenge1::{Base64Encoder, HexDecoder};
+use challenge2::{HexEncoder, Xor};
+use challenge6::Base64Decoder;
+use challenge21::{MT19937RNG, fill_bytes_offset, fill_bytes_outputs};
+use challenge22::{Clock, Observed, Strategy, recover_seeds, recover_seeds_with_progress};
+use challenge23::{observe_bytes, recover_state};
 
 const TEXT: &'static [u8; 14] = b"AAAAAAAAAAAAAA";
 
+// Jumping ahead costs about as much as generating millions of outputs, so
+// it only pays off for long distances.
+const JUMP_THRESHOLD: u64 = 1 << 24;
+
+// Eight keystream bytes give 64 bits to check, plenty for 2^32 seeds.
+const MAX_KNOWN_BYTES: usize = 8;
+
+// The number of state bits recover_state() needs to determine.
+const STATE_BITS: usize = 19937;
+
+#[derive(Debug, PartialEq)]
+pub enum SeedError {
+  // We need at least five known bytes to tell 2^32 seeds apart.
+  NotEnoughKnownPlaintext,
+  KnownPlaintextOutOfBounds,
+  NotFound
+}
+
 pub fn mt19337_transform(seed: u32, data: &[u8]) -> Vec<u8> {
   let mut rng = MT19937RNG::from_seed(seed);
   let key = rng.gen_iter().take(data.len());
   Vec::from_iter(key).xor(&data)
 }
 
+// An MT19937 stream cipher that uses all four bytes of every output,
+// little-endian. |position| counts
+// keystream bytes, |word| holds the bytes of the current output.
+pub struct MT19937Cipher {
+  seed: u32,
+  nonce: Option<u32>,
+  rng: MT19937RNG,
+  position: u64,
+  word: [u8; 4]
+}
+
+impl MT19937Cipher {
+  // The keystream is the output of MT19937RNG::from_seed(seed).
+  pub fn new(seed: u32) -> Self {
+    MT19937Cipher::init(seed, None)
+  }
+
+  // Mixes |nonce| into the initial state via init_by_array(), so that a
+  // single seed can encrypt more than one message.
+  pub fn with_nonce(seed: u32, nonce: u32) -> Self {
+    MT19937Cipher::init(seed, Some(nonce))
+  }
+
+  fn init(seed: u32, nonce: Option<u32>) -> Self {
+    let rng = MT19937Cipher::initial_rng(seed, nonce);
+    MT19937Cipher { seed: seed, nonce: nonce, rng: rng, position: 0, word: [0; 4] }
+  }
+
+  fn initial_rng(seed: u32, nonce: Option<u32>) -> MT19937RNG {
+    match nonce {
+      Some(nonce) => MT19937RNG::from_array(&[seed, nonce]),
+      None => MT19937RNG::from_seed(seed)
+    }
+  }
+
+  pub fn position(&self) -> u64 {
+    self.position
+  }
+
+  // Moves to keystream byte |position|, forwards or backwards.
+  pub fn seek(&mut self, position: u64) {
+    self.rng = MT19937Cipher::initial_rng(self.seed, self.nonce);
+    self.position = position;
+
+    let words = position / 4;
+    if words < JUMP_THRESHOLD {
+      self.rng.discard(words);
+    } else {
+      self.rng.jump(words);
+    }
+
+    // Load the current output if we're in the middle of it.
+    if position % 4 > 0 {
+      self.next_word();
+    }
+  }
+
+  fn next_word(&mut self) {
+    let word = self.rng.next_u32();
+
+    for i in 0..4 {
+      self.word[i] = (word >> (i * 8)) as u8;
+    }
+  }
+
+  pub fn apply_keystream(&mut self, data: &mut [u8]) {
+    for byte in data.iter_mut() {
+      let index = (self.position % 4) as usize;
+
+      if index == 0 {
+        self.next_word();
+      }
+
+      *byte ^= self.word[index];
+      self.position += 1;
+    }
+  }
+
+  pub fn transform(&mut self, data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    self.apply_keystream(&mut data);
+    data
+  }
+}
+
+// Decrypts (or encrypts) everything read from |inner|.
+pub struct MT19937Reader<R> {
+  inner: R,
+  cipher: MT19937Cipher
+}
+
+impl<R: Read> MT19937Reader<R> {
+  pub fn new(inner: R, cipher: MT19937Cipher) -> Self {
+    MT19937Reader { inner: inner, cipher: cipher }
+  }
+
+  pub fn into_inner(self) -> R {
+    self.inner
+  }
+}
+
+impl<R: Read> Read for MT19937Reader<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let len = try!(self.inner.read(buf));
+    self.cipher.apply_keystream(&mut buf[..len]);
+    Ok(len)
+  }
+}
+
+// Encrypts (or decrypts) everything written to |inner|.
+pub struct MT19937Writer<W> {
+  inner: W,
+  cipher: MT19937Cipher
+}
+
+impl<W: Write> MT19937Writer<W> {
+  pub fn new(inner: W, cipher: MT19937Cipher) -> Self {
+    MT19937Writer { inner: inner, cipher: cipher }
+  }
+
+  pub fn into_inner(self) -> W {
+    self.inner
+  }
+}
+
+impl<W: Write> Write for MT19937Writer<W> {
+  // The keystream has moved on once we xored |buf|, so we can't report
+  // partial writes and have to write everything.
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let data = self.cipher.transform(buf);
+    try!(self.inner.write_all(&data));
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.inner.flush()
+  }
+}
+
 pub struct BlackBox {
   seed: u32
 }
@@ -27,11 +193,14 @@ impl BlackBox {
     BlackBox { seed: rng.next_u32() & 0xffff }
   }
 
-  pub fn ticket() -> [u8; 16] {
-    let mut ticket = [0; 16];
-    let mut rng = MT19937RNG::from_seed(unix_time());
-    rng.fill_bytes(&mut ticket);
-    ticket
+  pub fn with_seed(seed: u32) -> Self {
+    BlackBox { seed: seed }
+  }
+
+  // A password reset token of 16 bytes, hex-encoded, from a generator
+  // seeded with the current time.
+  pub fn ticket<C: Clock>(clock: &C) -> String {
+    ticket_for_seed(clock.now())
   }
 
   pub fn encrypt(&self) -> Vec<u8> {
@@ -51,51 +220,343 @@ impl BlackBox {
   }
 }
 
-pub fn find_mt19337_seed(ciphertext: &[u8]) -> u32 {
+pub fn find_mt19337_seed(ciphertext: &[u8]) -> Option<u32> {
   // Try all 16 bit seeds.
-  for seed in 0..65536 {
-    let plaintext = mt19337_transform(seed, ciphertext);
-    if &plaintext[plaintext.len()-TEXT.len()..] == TEXT {
-      return seed;
-    }
+  (0..65536).find(|seed| {
+    let plaintext = mt19337_transform(*seed, ciphertext);
+    &plaintext[plaintext.len()-TEXT.len()..] == TEXT
+  })
+}
+
+// Recovers the seed of mt19337_transform() from |known| plaintext at
+// |offset|. Byte i of the keystream is the low byte of output i, so xoring
+// the known plaintext gives us a few outputs' low bytes. Candidate seeds
+// only need to produce these few outputs, instead of decrypting everything.
+pub fn recover_mt19337_seed<F>(ciphertext: &[u8], known: &[u8], offset: usize, seeds: Range<u64>, threads: usize, progress: &F) -> Result<u32, SeedError>
+    where F: Fn(u64, u64) + Sync
+{
+  if offset + known.len() > ciphertext.len() {
+    return Err(SeedError::KnownPlaintextOutOfBounds);
   }
 
-  panic!("shouldn't reach this");
+  if known.len() < 5 {
+    return Err(SeedError::NotEnoughKnownPlaintext);
+  }
+
+  let len = min(known.len(), MAX_KNOWN_BYTES);
+  let keystream = ciphertext[offset..offset + len].xor(&known[..len]);
+
+  // The low byte of output i is byte 4 * i of the outputs written out as
+  // little-endian words.
+  let observed = Vec::from_iter(keystream.iter().enumerate().map(|(i, byte)| {
+    Observed::Byte((offset + i) * 4, *byte)
+  }));
+
+  let seeds = recover_seeds_with_progress(&observed, seeds, Strategy::First, threads, progress);
+  seeds.first().cloned().ok_or(SeedError::NotFound)
 }
 
-pub fn is_ticket(ticket: &[u8; 16]) -> bool {
-  let now = unix_time();
-  let mut candidate = [0; 16];
+pub fn is_ticket<C: Clock>(ticket: &str, clock: &C) -> bool {
+  let now = clock.now();
 
   // Check seeds around current unix time.
-  (now-5..now+5).any(|seed| {
-    let mut rng = MT19937RNG::from_seed(seed);
-    rng.fill_bytes(&mut candidate);
-    &candidate == ticket
-  })
+  (now-5..now+5).any(|seed| ticket_for_seed(seed) == ticket)
+}
+
+fn ticket_for_seed(seed: u32) -> String {
+  TokenGenerator::new(MT19937RNG::from_seed(seed), 16, TokenEncoding::Hex).token()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenEncoding {
+  Hex,
+  Base64
+}
+
+impl TokenEncoding {
+  fn encode(&self, bytes: &[u8]) -> String {
+    match *self {
+      TokenEncoding::Hex => bytes.to_hex(),
+      TokenEncoding::Base64 => bytes.to_base64()
+    }
+  }
+
+  fn decode(&self, token: &str) -> Vec<u8> {
+    match *self {
+      TokenEncoding::Hex => token.from_hex(),
+      TokenEncoding::Base64 => token.from_base64()
+    }
+  }
+}
+
+// Issues tokens of |len| random bytes. Any Rng works, e.g. MT19937RNG,
+// OsRng, or ChaChaRng.
+pub struct TokenGenerator<R> {
+  rng: R,
+  len: usize,
+  encoding: TokenEncoding
+}
+
+impl<R: Rng> TokenGenerator<R> {
+  pub fn new(rng: R, len: usize, encoding: TokenEncoding) -> Self {
+    TokenGenerator { rng: rng, len: len, encoding: encoding }
+  }
+
+  pub fn token(&mut self) -> String {
+    let mut bytes = vec!(0; self.len);
+    self.rng.fill_bytes(&mut bytes);
+    self.encoding.encode(&bytes)
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TokenSource {
+  // A single generator, seeded with the given time, issued all tokens.
+  TimeSeeded(u32),
+  // Every token came from a fresh generator seeded with its issue time.
+  TimeSeededPerToken(Vec<u32>),
+  // The tokens revealed enough outputs to clone the generator.
+  Cloned,
+  // Not MT19937 as far as we can tell, e.g. a CSPRNG.
+  Unknown
+}
+
+pub struct TokenAnalysis {
+  pub source: TokenSource,
+  len: usize,
+  encoding: TokenEncoding,
+  // The generator that issues the next token, if there is a single one.
+  generator: Option<TokenGenerator<MT19937RNG>>
+}
+
+impl TokenAnalysis {
+  // The next |n| tokens, if a single generator issues them.
+  pub fn predict(&mut self, n: usize) -> Vec<String> {
+    match self.generator {
+      Some(ref mut generator) => Vec::from_iter((0..n).map(|_| generator.token())),
+      None => vec!()
+    }
+  }
+
+  // The tokens issued at |times|, if every token is seeded with its time.
+  pub fn predict_at(&self, times: Range<u32>) -> Vec<String> {
+    match self.source {
+      TokenSource::TimeSeededPerToken(_) => Vec::from_iter(times.map(|time| {
+        let rng = MT19937RNG::from_seed(time);
+        TokenGenerator::new(rng, self.len, self.encoding).token()
+      })),
+      _ => vec!()
+    }
+  }
+}
+
+// Checks whether the given consecutive |tokens| of |len| bytes came from
+// MT19937. We first look for seeds in |issued|, the approximate issue time,
+// e.g. from time_window(). If that fails and there are enough tokens, we try
+// to recover the generator state from their bits. Every candidate must
+// reproduce all tokens.
+pub fn analyze_tokens(tokens: &[String], len: usize, encoding: TokenEncoding, issued: Range<u64>) -> TokenAnalysis {
+  assert!(tokens.len() > 0 && len > 0);
+
+  let mut analysis = TokenAnalysis { source: TokenSource::Unknown, len: len, encoding: encoding, generator: None };
+
+  // The generator reproduces |tokens|, then is ready to issue the next one.
+  let reproduces = |rng: MT19937RNG, tokens: &[String]| {
+    let mut generator = TokenGenerator::new(rng, len, encoding);
+    if tokens.iter().all(|token| generator.token() == *token) { Some(generator) } else { None }
+  };
+
+  // Seeds in |issued| whose first outputs match the start of |token|.
+  let seeds_for = |token: &str| {
+    let bytes = encoding.decode(token);
+    let known = min(bytes.len(), MAX_KNOWN_BYTES);
+    let observed = Vec::from_iter((0..known).map(|i| Observed::Byte(fill_bytes_offset(i), bytes[i])));
+    recover_seeds(&observed, issued.clone(), Strategy::All, 1)
+  };
+
+  // Did a single time-seeded generator issue all tokens?
+  for seed in seeds_for(&tokens[0]) {
+    if let Some(generator) = reproduces(MT19937RNG::from_seed(seed), tokens) {
+      analysis.source = TokenSource::TimeSeeded(seed);
+      analysis.generator = Some(generator);
+      return analysis;
+    }
+  }
+
+  // Or was every token issued by a freshly seeded generator?
+  let mut seeds = vec!();
+  for token in tokens {
+    let seed = seeds_for(token).into_iter().find(|seed| {
+      reproduces(MT19937RNG::from_seed(*seed), &[token.clone()]).is_some()
+    });
+
+    match seed {
+      Some(seed) => seeds.push(seed),
+      None => break
+    }
+  }
+
+  if seeds.len() == tokens.len() {
+    analysis.source = TokenSource::TimeSeededPerToken(seeds);
+    return analysis;
+  }
+
+  // Don't bother if there are fewer bits than unknowns.
+  if tokens.len() * len * 8 < STATE_BITS {
+    return analysis;
+  }
+
+  // Each token uses whole pairs of outputs, fill_bytes() drops leftovers.
+  let outputs = fill_bytes_outputs(len);
+  let observations = Vec::from_iter(tokens.iter().enumerate().flat_map(|(i, token)| {
+    observe_bytes(i * outputs, &encoding.decode(token)).into_iter()
+  }));
+
+  if let Some(generator) = recover_state(&observations).and_then(|rng| reproduces(rng, tokens)) {
+    analysis.source = TokenSource::Cloned;
+    analysis.generator = Some(generator);
+  }
+
+  analysis
 }
 
 #[cfg(test)]
 mod test {
+  use challenge1::HexDecoder;
+  use challenge6::Base64Decoder;
+  use challenge21::{MT19937RNG, fill_bytes_offset};
+  use challenge22::{Clock, Observed, SimulatedClock, Strategy, recover_seeds, time_window};
+  use rand::{ChaChaRng, OsRng, Rng, SeedableRng};
+  use std::io::{Cursor, Read, Write};
+  use std::iter::FromIterator;
+  use std::sync::atomic::{AtomicUsize, Ordering};
   use TEXT;
   use BlackBox;
+  use MT19937Cipher;
+  use MT19937Reader;
+  use MT19937Writer;
+  use SeedError;
+  use TokenEncoding;
+  use TokenGenerator;
+  use TokenSource;
+  use analyze_tokens;
   use is_ticket;
   use mt19337_transform;
   use find_mt19337_seed;
+  use recover_mt19337_seed;
 
   #[test]
   fn test() {
     let blackbox = BlackBox::new();
     let ciphertext = blackbox.encrypt();
-    let seed = find_mt19337_seed(&ciphertext);
+    let seed = find_mt19337_seed(&ciphertext).unwrap();
     let plaintext = mt19337_transform(seed, &ciphertext);
     assert_eq!(&plaintext[plaintext.len()-TEXT.len()..], TEXT);
   }
 
   #[test]
   fn test_ticket() {
-    let ticket = BlackBox::ticket();
-    assert!(is_ticket(&ticket));
+    let clock = SimulatedClock::new(1436000000);
+    let ticket = BlackBox::ticket(&clock);
+    assert!(is_ticket(&ticket, &clock));
+
+    // Still valid a few seconds later, but not after a minute.
+    clock.sleep(4);
+    assert!(is_ticket(&ticket, &clock));
+    clock.sleep(60);
+    assert!(!is_ticket(&ticket, &clock));
+  }
+
+  #[test]
+  fn test_ticket_seed() {
+    let clock = SimulatedClock::new(1436000000);
+    let ticket = BlackBox::ticket(&clock);
+    assert_eq!(ticket.len(), 32);
+    clock.sleep(100);
+
+    // Every four bytes of the ticket are a full output, fill_bytes() swaps
+    // the two outputs of each pair.
+    let bytes = ticket.from_hex();
+    let observed = Vec::from_iter(bytes.chunks(4).enumerate().map(|(i, chunk)| {
+      let word = chunk.iter().rev().fold(0u32, |word, byte| (word << 8) | *byte as u32);
+      Observed::Output(fill_bytes_offset(i * 4) / 4, word)
+    }));
+
+    let seeds = recover_seeds(&observed, time_window(&clock, 300, 0), Strategy::All, 1);
+    assert_eq!(seeds, vec!(1436000000));
+  }
+
+  #[test]
+  fn test_cipher() {
+    let data = b"This is a longer test with an odd length.";
+    let ciphertext = MT19937Cipher::new(12345).transform(data);
+    assert_eq!(MT19937Cipher::new(12345).transform(&ciphertext), data.to_vec());
+
+    // The keystream is every output, little-endian.
+    let mut rng = MT19937RNG::from_seed(12345);
+    let keystream = Vec::from_iter((0..11).flat_map(|_| {
+      let word = rng.next_u32();
+      (0..4).map(move |i| (word >> (i * 8)) as u8)
+    }));
+    assert_eq!(MT19937Cipher::new(12345).transform(&[0; 41]), &keystream[..41]);
+
+    // Other nonces give other keystreams.
+    let first = MT19937Cipher::with_nonce(12345, 1).transform(data);
+    let second = MT19937Cipher::with_nonce(12345, 2).transform(data);
+    assert!(first != ciphertext && first != second);
+    assert_eq!(MT19937Cipher::with_nonce(12345, 1).transform(&first), data.to_vec());
+  }
+
+  #[test]
+  fn test_cipher_seek() {
+    let keystream = MT19937Cipher::with_nonce(1, 2).transform(&[0; 3000]);
+
+    let mut cipher = MT19937Cipher::with_nonce(1, 2);
+    for offset in vec!(2999, 7, 0, 2496, 1234) {
+      cipher.seek(offset);
+      assert_eq!(cipher.position(), offset);
+      assert_eq!(cipher.transform(&[0; 1]), vec!(keystream[offset as usize]));
+      assert_eq!(cipher.position(), offset + 1);
+    }
+
+    // Far offsets jump ahead.
+    let mut rng = MT19937RNG::from_seed(1);
+    rng.jump(1 << 30);
+    rng.next_u32();
+    let word = rng.next_u32();
+
+    let mut cipher = MT19937Cipher::new(1);
+    cipher.seek((1 << 32) + 5);
+    assert_eq!(cipher.transform(&[0; 3]), vec!((word >> 8) as u8, (word >> 16) as u8, (word >> 24) as u8));
+  }
+
+  #[test]
+  fn test_cipher_streams() {
+    let data = Vec::from_iter((0..1000).map(|i| i as u8));
+    let ciphertext = MT19937Cipher::with_nonce(7, 42).transform(&data);
+
+    // Write in chunks of odd sizes.
+    let mut writer = MT19937Writer::new(Vec::new(), MT19937Cipher::with_nonce(7, 42));
+    for chunk in data.chunks(13) {
+      writer.write_all(chunk).unwrap();
+    }
+    assert_eq!(writer.into_inner(), ciphertext);
+
+    // Read in chunks of odd sizes.
+    let cursor = Cursor::new(ciphertext.clone());
+    let mut reader = MT19937Reader::new(cursor, MT19937Cipher::with_nonce(7, 42));
+    let mut plaintext = Vec::new();
+    let mut buf = [0; 7];
+
+    loop {
+      let len = reader.read(&mut buf).unwrap();
+      if len == 0 {
+        break;
+      }
+      plaintext.extend(buf[..len].to_vec());
+    }
+
+    assert_eq!(plaintext, data);
   }
 
   #[test]
@@ -108,4 +569,119 @@ mod test {
     let plaintext = mt19337_transform(12345, &ciphertext);
     assert_eq!(plaintext, data);
   }
+
+  #[test]
+  fn test_recover_seed() {
+    let blackbox = BlackBox::new();
+    let ciphertext = blackbox.encrypt();
+    let offset = ciphertext.len() - TEXT.len();
+
+    let seed = recover_mt19337_seed(&ciphertext, TEXT, offset, 0..65536, 1, &|_, _| {}).unwrap();
+    assert_eq!(seed, blackbox.seed);
+  }
+
+  #[test]
+  fn test_recover_seed_32bit() {
+    let blackbox = BlackBox::with_seed(0xc0ffee42);
+    let ciphertext = blackbox.encrypt();
+    let offset = ciphertext.len() - TEXT.len();
+    assert_eq!(find_mt19337_seed(&ciphertext), None);
+
+    // Search the upper part of the seed space, with progress reports.
+    let checked = AtomicUsize::new(0);
+    let seed = recover_mt19337_seed(&ciphertext, TEXT, offset, 0xc0f00000..1 << 32, 4, &|done, _| {
+      checked.store(done as usize, Ordering::SeqCst);
+    });
+
+    assert_eq!(seed, Ok(0xc0ffee42));
+    assert!(checked.load(Ordering::SeqCst) > 0xfee42);
+  }
+
+  #[test]
+  fn test_recover_seed_errors() {
+    let ciphertext = BlackBox::with_seed(1).encrypt();
+    let noop = |_, _| {};
+
+    assert_eq!(recover_mt19337_seed(&ciphertext, b"AAAA", 0, 0..10, 1, &noop), Err(SeedError::NotEnoughKnownPlaintext));
+    assert_eq!(recover_mt19337_seed(&ciphertext, TEXT, ciphertext.len(), 0..10, 1, &noop), Err(SeedError::KnownPlaintextOutOfBounds));
+    assert_eq!(recover_mt19337_seed(&ciphertext, TEXT, ciphertext.len() - TEXT.len(), 2..10, 1, &noop), Err(SeedError::NotFound));
+  }
+
+  #[test]
+  fn test_token_generator() {
+    let mut generator = TokenGenerator::new(MT19937RNG::from_seed(1), 16, TokenEncoding::Hex);
+    let token = generator.token();
+    assert_eq!(token.len(), 32);
+    assert!(generator.token() != token);
+
+    let mut keystream = [0; 16];
+    MT19937RNG::from_seed(1).fill_bytes(&mut keystream);
+    assert_eq!(token.from_hex(), keystream.to_vec());
+
+    let mut generator = TokenGenerator::new(OsRng::new().unwrap(), 18, TokenEncoding::Base64);
+    let token = generator.token();
+    assert_eq!(token.len(), 24);
+    assert_eq!(token.from_base64().len(), 18);
+
+    let mut generator = TokenGenerator::new(ChaChaRng::new_unseeded(), 16, TokenEncoding::Base64);
+    assert_eq!(generator.token().from_base64().len(), 16);
+  }
+
+  #[test]
+  fn test_analyze_time_seeded() {
+    let clock = SimulatedClock::new(1436000000);
+    let rng = MT19937RNG::from_seed(clock.now());
+    let mut generator = TokenGenerator::new(rng, 16, TokenEncoding::Base64);
+    let tokens = Vec::from_iter((0..3).map(|_| generator.token()));
+    clock.sleep(120);
+
+    let mut analysis = analyze_tokens(&tokens, 16, TokenEncoding::Base64, time_window(&clock, 600, 0));
+    assert_eq!(analysis.source, TokenSource::TimeSeeded(1436000000));
+    assert_eq!(analysis.predict(2), vec!(generator.token(), generator.token()));
+  }
+
+  #[test]
+  fn test_analyze_per_token() {
+    let clock = SimulatedClock::new(1436000000);
+    let first = BlackBox::ticket(&clock);
+    clock.sleep(30);
+    let second = BlackBox::ticket(&clock);
+    clock.sleep(30);
+
+    let tokens = vec!(first, second);
+    let analysis = analyze_tokens(&tokens, 16, TokenEncoding::Hex, time_window(&clock, 300, 0));
+    assert_eq!(analysis.source, TokenSource::TimeSeededPerToken(vec!(1436000000, 1436000030)));
+
+    // We know the ticket that will be issued in a minute.
+    let now = clock.now();
+    let predicted = analysis.predict_at(now + 55..now + 65);
+    clock.sleep(60);
+    assert!(predicted.contains(&BlackBox::ticket(&clock)));
+  }
+
+  #[test]
+  fn test_analyze_cloned() {
+    // Not time-seeded, and each token drops two bytes of an output.
+    let rng = MT19937RNG::from_seed(0xdeadbeef);
+    let mut generator = TokenGenerator::new(rng, 14, TokenEncoding::Base64);
+    let tokens = Vec::from_iter((0..260).map(|_| generator.token()));
+
+    let analysis = analyze_tokens(&tokens[..100], 14, TokenEncoding::Base64, 1436000000..1436000600);
+    assert_eq!(analysis.source, TokenSource::Unknown);
+
+    let mut analysis = analyze_tokens(&tokens, 14, TokenEncoding::Base64, 1436000000..1436000600);
+    assert_eq!(analysis.source, TokenSource::Cloned);
+    assert_eq!(analysis.predict(3), Vec::from_iter((0..3).map(|_| generator.token())));
+  }
+
+  #[test]
+  fn test_analyze_unknown() {
+    let mut generator = TokenGenerator::new(OsRng::new().unwrap(), 16, TokenEncoding::Hex);
+    let tokens = Vec::from_iter((0..200).map(|_| generator.token()));
+
+    let mut analysis = analyze_tokens(&tokens, 16, TokenEncoding::Hex, 1436000000..1436000600);
+    assert_eq!(analysis.source, TokenSource::Unknown);
+    assert!(analysis.predict(1).is_empty());
+    assert!(analysis.predict_at(0..10).is_empty());
+  }
 }
@@ -0,0 +1,115 @@
+extern crate rand;
+extern crate challenge2;
+extern crate challenge4;
+extern crate challenge6;
+extern crate challenge7;
+extern crate challenge18;
+
+use rand::{Rng, OsRng};
+use std::iter::{FromIterator, repeat};
+use challenge2::Xor;
+use challenge18::{aes_128_ctr_transform, AesCtr};
+
+// Re-encrypts |newtext| at |offset| in place, without touching the rest of
+// the ciphertext.
+pub fn edit(key: &[u8], nonce: u64, ciphertext: &mut [u8], offset: usize, newtext: &[u8]) {
+  assert!(offset + newtext.len() <= ciphertext.len());
+
+  let mut ctr = AesCtr::cryptopals(key, nonce);
+  ctr.seek(offset as u64);
+
+  for (dst, src) in ciphertext[offset..].iter_mut().zip(ctr.update(newtext)) {
+    *dst = src;
+  }
+}
+
+// Holds a ciphertext encrypted under a secret key and nonce. All we can do is
+// look at the ciphertext and edit it.
+pub struct BlackBox {
+  key: [u8; 16],
+  nonce: u64,
+  ciphertext: Vec<u8>
+}
+
+impl BlackBox {
+  pub fn new(plaintext: &[u8]) -> BlackBox {
+    let mut rng = OsRng::new().unwrap();
+
+    // Generate a random key.
+    let mut key = [0u8; 16];
+    rng.fill_bytes(&mut key);
+
+    let nonce = rng.next_u64();
+    let ciphertext = aes_128_ctr_transform(&key, nonce, plaintext);
+    BlackBox { key: key, nonce: nonce, ciphertext: ciphertext }
+  }
+
+  pub fn ciphertext(&self) -> &[u8] {
+    &self.ciphertext
+  }
+
+  pub fn edit(&mut self, offset: usize, newtext: &[u8]) {
+    edit(&self.key, self.nonce, &mut self.ciphertext, offset, newtext);
+  }
+}
+
+// Overwriting the whole ciphertext with zeros gives us the keystream. We put
+// the plaintext back afterwards, so nobody notices.
+pub fn recover_plaintext(blackbox: &mut BlackBox) -> Vec<u8> {
+  let ciphertext = blackbox.ciphertext().to_vec();
+  let zeros = Vec::from_iter(repeat(0u8).take(ciphertext.len()));
+
+  blackbox.edit(0, &zeros);
+  let plaintext = blackbox.ciphertext().xor(&ciphertext);
+  blackbox.edit(0, &plaintext);
+
+  plaintext
+}
+
+#[cfg(test)]
+mod test {
+  use challenge4::read_file;
+  use challenge6::Base64Decoder;
+  use challenge7::aes_128_ecb_decrypt;
+  use challenge18::aes_128_ctr_transform;
+  use edit;
+  use recover_plaintext;
+  use BlackBox;
+
+  #[test]
+  fn test() {
+    let data = read_file("../challenge7/data.txt").replace("\n", "").from_base64();
+    let secret = aes_128_ecb_decrypt(b"YELLOW SUBMARINE", &data);
+
+    let mut blackbox = BlackBox::new(&secret);
+    let ciphertext = blackbox.ciphertext().to_vec();
+
+    assert_eq!(recover_plaintext(&mut blackbox), secret);
+    assert_eq!(blackbox.ciphertext(), &ciphertext[..]);
+  }
+
+  #[test]
+  fn test_edit() {
+    let mut blackbox = BlackBox::new(b"attack at dawn, not at dusk");
+    let ciphertext = blackbox.ciphertext().to_vec();
+
+    // Only the edited bytes change.
+    blackbox.edit(10, b"noon");
+    assert_eq!(&blackbox.ciphertext()[..10], &ciphertext[..10]);
+    assert_eq!(&blackbox.ciphertext()[14..], &ciphertext[14..]);
+    assert_eq!(recover_plaintext(&mut blackbox), b"attack at noon, not at dusk".to_vec());
+
+    // Edits across block boundaries.
+    blackbox.edit(13, b"n, but at");
+    assert_eq!(recover_plaintext(&mut blackbox), b"attack at noon, but at dusk".to_vec());
+  }
+
+  #[test]
+  fn test_edit_function() {
+    let key = b"YELLOW SUBMARINE";
+    let mut ciphertext = aes_128_ctr_transform(key, 7, b"attack at dawn");
+
+    edit(key, 7, &mut ciphertext, 10, b"noon");
+    assert_eq!(aes_128_ctr_transform(key, 7, &ciphertext), b"attack at noon".to_vec());
+  }
+}
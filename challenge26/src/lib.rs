@@ -0,0 +1,101 @@
+extern crate rand;
+extern crate challenge16;
+extern crate challenge18;
+
+use rand::{Rng, OsRng};
+use challenge16::{BitFlipError, placeholder_for};
+use challenge18::aes_128_ctr_transform;
+
+static PREFIX: &'static[u8] = b"comment1=cooking%20MCs;userdata=";
+static POSTFIX: &'static[u8] = b";comment2=%20like%20a%20pound%20of%20bacon";
+
+pub struct BlackBox {
+  key: [u8; 16],
+  nonce: u64
+}
+
+impl BlackBox {
+  pub fn new() -> BlackBox {
+    let mut rng = OsRng::new().unwrap();
+
+    // Generate a random key.
+    let mut key = [0u8; 16];
+    rng.fill_bytes(&mut key);
+
+    BlackBox { key: key, nonce: rng.next_u64() }
+  }
+
+  pub fn encrypt(&self, input: &str) -> Vec<u8> {
+    // Remove meta chars.
+    let input = input.replace(";", "");
+    let input = input.replace("=", "");
+
+    // Sandwich data between prefix and postfix.
+    let mut data = PREFIX.to_vec();
+    data.extend(input.as_bytes().to_vec());
+    data.extend(POSTFIX.to_vec());
+
+    // Encrypt.
+    aes_128_ctr_transform(&self.key, self.nonce, &data)
+  }
+
+  pub fn is_admin(&self, data: &[u8]) -> bool {
+    let needle = b";admin=true;";
+    let decryption = aes_128_ctr_transform(&self.key, self.nonce, data);
+
+    decryption.windows(needle.len()).any(|window| window == needle)
+  }
+}
+
+// Flipping a bit of a CTR ciphertext flips the same bit of the plaintext and
+// nothing else. So unlike CBC there are no blocks to align or scramble.
+pub fn ctr_bitflip<F>(encrypt: &F, prefix_len: usize, inject: &[u8], forbidden: &[u8]) -> Result<Vec<u8>, BitFlipError>
+    where F: Fn(&str) -> Vec<u8>
+{
+  let placeholder = try!(placeholder_for(inject, forbidden));
+  let input = try!(String::from_utf8(placeholder.clone()).map_err(|_| BitFlipError::NotUtf8));
+  let mut ciphertext = encrypt(&input);
+
+  for (i, (a, b)) in inject.iter().zip(placeholder.iter()).enumerate() {
+    ciphertext[prefix_len + i] ^= a ^ b;
+  }
+
+  Ok(ciphertext)
+}
+
+#[cfg(test)]
+mod test {
+  use challenge16::BitFlipError;
+  use BlackBox;
+  use ctr_bitflip;
+
+  #[test]
+  fn test() {
+    let blackbox = BlackBox::new();
+    let encrypt = |input: &str| blackbox.encrypt(input);
+
+    let forged = ctr_bitflip(&encrypt, 32, b";admin=true;", b";=").unwrap();
+    assert!(blackbox.is_admin(&forged));
+
+    // Meta chars can be anywhere.
+    let forged = ctr_bitflip(&encrypt, 32, b"a=b;c=d;e=f;g=h;i=j;admin=true;k=l", b";=").unwrap();
+    assert!(blackbox.is_admin(&forged));
+
+    // The oracle only takes strings.
+    let forged = ctr_bitflip(&encrypt, 32, b";admin=true;\xff", b";=");
+    assert_eq!(forged.unwrap_err(), BitFlipError::NotUtf8);
+  }
+
+  #[test]
+  fn test_encrypt() {
+    let blackbox = BlackBox::new();
+    let encrypted = blackbox.encrypt("asdf");
+    assert!(!blackbox.is_admin(&encrypted));
+
+    let encrypted = blackbox.encrypt(";admin=true;");
+    assert!(!blackbox.is_admin(&encrypted));
+
+    // Too short for the needle.
+    assert!(!blackbox.is_admin(&encrypted[..8]));
+  }
+}
@@ -0,0 +1,125 @@
+extern crate rand;
+extern crate challenge2;
+extern crate challenge9;
+extern crate challenge10;
+extern crate challenge15;
+
+use rand::{Rng, OsRng};
+use std::iter::repeat;
+use challenge2::Xor;
+use challenge9::PKCS7Pad;
+use challenge10::{aes_128_cbc_encrypt, aes_128_cbc_decrypt};
+use challenge15::PKCS7Unpad;
+
+static PREFIX: &'static[u8] = b"comment1=cooking%20MCs;userdata=";
+static POSTFIX: &'static[u8] = b";comment2=%20like%20a%20pound%20of%20bacon";
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+  // Echoes the offending plaintext, like a careless server would.
+  InvalidAscii(Vec<u8>),
+  InvalidPadding
+}
+
+pub struct BlackBox {
+  key: [u8; 16]
+}
+
+impl BlackBox {
+  pub fn new() -> BlackBox {
+    let mut rng = OsRng::new().unwrap();
+
+    // Generate a random key.
+    let mut key = [0u8; 16];
+    rng.fill_bytes(&mut key);
+
+    BlackBox { key: key }
+  }
+
+  pub fn encrypt(&self, input: &str) -> Vec<u8> {
+    // Remove meta chars.
+    let input = input.replace(";", "");
+    let input = input.replace("=", "");
+
+    // Sandwich data between prefix and postfix.
+    let mut data = PREFIX.to_vec();
+    data.extend(input.as_bytes().to_vec());
+    data.extend(POSTFIX.to_vec());
+
+    // Pad to block size.
+    let data = data.pkcs7_pad(16);
+
+    // Encrypt, using the key as the IV. The IV isn't sent along, that would
+    // give away the key right away.
+    aes_128_cbc_encrypt(&self.key, &data, self.key.to_vec())
+  }
+
+  pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+    let decrypted = aes_128_cbc_decrypt(&self.key, data, self.key.to_vec());
+
+    // Complain about high-ASCII bytes.
+    if decrypted.iter().any(|byte| *byte > 127) {
+      return Err(Error::InvalidAscii(decrypted));
+    }
+
+    decrypted.pkcs7_unpad().ok_or(Error::InvalidPadding)
+  }
+}
+
+// With C_1, 0, C_1 as the first three blocks the decryption yields
+// P'_1 = D(C_1) ^ IV and P'_3 = D(C_1) ^ 0. As IV = key, P'_1 ^ P'_3 = key.
+// The rest of the ciphertext is kept so that the padding stays valid.
+pub fn recover_key<F>(ciphertext: &[u8], decrypt: F) -> Option<Vec<u8>>
+    where F: Fn(&[u8]) -> Result<Vec<u8>, Error>
+{
+  if ciphertext.len() < 48 {
+    return None;
+  }
+
+  let mut modified = ciphertext[..16].to_vec();
+  modified.extend(repeat(0u8).take(16));
+  modified.extend(ciphertext[..16].iter().cloned());
+  modified.extend(ciphertext[48..].iter().cloned());
+
+  // We need the error to see the plaintext. Chances that the two scrambled
+  // blocks decrypt to plain ASCII are about 1 in 2^32.
+  match decrypt(&modified) {
+    Err(Error::InvalidAscii(plaintext)) => Some(plaintext[..16].xor(&plaintext[32..48])),
+    _ => None
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use challenge10::aes_128_cbc_decrypt;
+  use BlackBox;
+  use recover_key;
+
+  #[test]
+  fn test() {
+    let blackbox = BlackBox::new();
+    let ciphertext = blackbox.encrypt("attack at dawn");
+
+    let key = recover_key(&ciphertext, |data| blackbox.decrypt(data)).unwrap();
+    assert_eq!(key, blackbox.key.to_vec());
+
+    // With the key we can decrypt everything.
+    let plaintext = aes_128_cbc_decrypt(&key, &ciphertext, key.clone());
+    assert!(plaintext.starts_with(b"comment1=cooking%20MCs;userdata=attack at dawn;"));
+  }
+
+  #[test]
+  fn test_decrypt() {
+    let blackbox = BlackBox::new();
+    let ciphertext = blackbox.encrypt("attack at dawn");
+
+    let plaintext = blackbox.decrypt(&ciphertext).unwrap();
+    assert!(plaintext.ends_with(b"userdata=attack at dawn;comment2=%20like%20a%20pound%20of%20bacon"));
+  }
+
+  #[test]
+  fn test_short_ciphertext() {
+    let blackbox = BlackBox::new();
+    assert_eq!(recover_key(b"YELLOW SUBMARINE", |data| blackbox.decrypt(data)), None);
+  }
+}
@@ -0,0 +1,213 @@
+extern crate rand;
+extern crate openssl;
+extern crate bignum;
+
+use bignum::BigUint;
+use openssl::crypto::hash::{hash, Type};
+use rand::Rng;
+
+// RFC 3526, group 5.
+static MODP_1536: &'static str = concat!(
+  "ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74",
+  "020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437",
+  "4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed",
+  "ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05",
+  "98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb",
+  "9ed529077096966d670c354e4abc9804f1746c08ca237327ffffffffffffffff"
+);
+
+// RFC 3526, group 14.
+static MODP_2048: &'static str = concat!(
+  "ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74",
+  "020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437",
+  "4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed",
+  "ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05",
+  "98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb",
+  "9ed529077096966d670c354e4abc9804f1746c08ca18217c32905e462e36ce3b",
+  "e39e772c180e86039b2783a2ec07a28fb5c55df06f4c52c9de2bcbf695581718",
+  "3995497cea956ae515d2261898fa051015728e5a8aacaa68ffffffffffffffff"
+);
+
+// RFC 3526, group 15.
+static MODP_3072: &'static str = concat!(
+  "ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74",
+  "020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437",
+  "4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed",
+  "ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05",
+  "98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb",
+  "9ed529077096966d670c354e4abc9804f1746c08ca18217c32905e462e36ce3b",
+  "e39e772c180e86039b2783a2ec07a28fb5c55df06f4c52c9de2bcbf695581718",
+  "3995497cea956ae515d2261898fa051015728e5a8aaac42dad33170d04507a33",
+  "a85521abdf1cba64ecfb850458dbef0a8aea71575d060c7db3970f85a6e1e4c7",
+  "abf5ae8cdb0933d71e8c94e04a25619dcee3d2261ad2ee6bf12ffa06d98a0864",
+  "d87602733ec86a64521f2b18177b200cbbe117577a615d6c770988c0bad946e2",
+  "08e24fa074e5ab3143db5bfce0fd108e4b82d120a93ad2caffffffffffffffff"
+);
+
+// RFC 3526, group 16.
+static MODP_4096: &'static str = concat!(
+  "ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74",
+  "020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437",
+  "4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed",
+  "ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05",
+  "98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb",
+  "9ed529077096966d670c354e4abc9804f1746c08ca18217c32905e462e36ce3b",
+  "e39e772c180e86039b2783a2ec07a28fb5c55df06f4c52c9de2bcbf695581718",
+  "3995497cea956ae515d2261898fa051015728e5a8aaac42dad33170d04507a33",
+  "a85521abdf1cba64ecfb850458dbef0a8aea71575d060c7db3970f85a6e1e4c7",
+  "abf5ae8cdb0933d71e8c94e04a25619dcee3d2261ad2ee6bf12ffa06d98a0864",
+  "d87602733ec86a64521f2b18177b200cbbe117577a615d6c770988c0bad946e2",
+  "08e24fa074e5ab3143db5bfce0fd108e4b82d120a92108011a723c12a787e6d7",
+  "88719a10bdba5b2699c327186af4e23c1a946834b6150bda2583e9ca2ad44ce8",
+  "dbbbc2db04de8ef92e8efc141fbecaa6287c59474e6bc05d99b2964fa090c3a2",
+  "233ba186515be7ed1f612970cee2d7afb81bdd762170481cd0069127d5b05aa9",
+  "93b4ea988d8fddc186ffb7dc90a6c08f4df435c934063199ffffffffffffffff"
+);
+
+// A finite-field Diffie-Hellman group.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Group {
+  pub p: BigUint,
+  pub g: BigUint
+}
+
+impl Group {
+  pub fn new(p: BigUint, g: BigUint) -> Group {
+    Group { p: p, g: g }
+  }
+
+  // The toy group from the challenge, for testing only.
+  pub fn small() -> Group {
+    Group::new(BigUint::from_u64(37), BigUint::from_u64(5))
+  }
+
+  // The 1536-bit MODP group, also called the NIST prime by the challenge.
+  pub fn modp_1536() -> Group {
+    Group::new(BigUint::from_hex(MODP_1536), BigUint::from_u64(2))
+  }
+
+  pub fn modp_2048() -> Group {
+    Group::new(BigUint::from_hex(MODP_2048), BigUint::from_u64(2))
+  }
+
+  pub fn modp_3072() -> Group {
+    Group::new(BigUint::from_hex(MODP_3072), BigUint::from_u64(2))
+  }
+
+  pub fn modp_4096() -> Group {
+    Group::new(BigUint::from_hex(MODP_4096), BigUint::from_u64(2))
+  }
+
+  // Picks a private key in 1..p-1.
+  pub fn keypair<R: Rng>(&self, rng: &mut R) -> KeyPair {
+    let bound = &self.p - &BigUint::from_u64(2);
+    let private = &random_below(rng, &bound) + &BigUint::one();
+    self.keypair_from(private)
+  }
+
+  pub fn keypair_from(&self, private: BigUint) -> KeyPair {
+    let public = self.g.modpow(&private, &self.p);
+    KeyPair { private: private, public: public }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyPair {
+  pub private: BigUint,
+  pub public: BigUint
+}
+
+impl KeyPair {
+  pub fn shared_secret(&self, group: &Group, public: &BigUint) -> BigUint {
+    public.modpow(&self.private, &group.p)
+  }
+}
+
+// A uniformly random number in 0..bound, by rejection sampling.
+pub fn random_below<R: Rng>(rng: &mut R, bound: &BigUint) -> BigUint {
+  assert!(!bound.is_zero());
+
+  let bits = bound.bits();
+  let mut bytes = vec!(0u8; (bits + 7) / 8);
+
+  loop {
+    rng.fill_bytes(&mut bytes);
+
+    // Clear the excess bits of the most significant byte.
+    if bits % 8 > 0 {
+      bytes[0] &= (1 << (bits % 8)) - 1;
+    }
+
+    let n = BigUint::from_bytes_be(&bytes);
+    if &n < bound {
+      return n;
+    }
+  }
+}
+
+// The AES-128 key for a shared secret: the first half of SHA1(s).
+pub fn derive_key(secret: &BigUint) -> Vec<u8> {
+  hash(Type::SHA1, &secret.to_bytes_be())[..16].to_vec()
+}
+
+#[cfg(test)]
+mod test {
+  use bignum::BigUint;
+  use rand::OsRng;
+  use Group;
+  use derive_key;
+  use random_below;
+
+  #[test]
+  fn test() {
+    let mut rng = OsRng::new().unwrap();
+
+    // The small group has no secrets.
+    let group = Group::small();
+    let a = group.keypair_from(BigUint::from_u64(6));
+    let b = group.keypair_from(BigUint::from_u64(15));
+    assert_eq!(a.public, BigUint::from_u64(11));
+    assert_eq!(b.public, BigUint::from_u64(29));
+    assert_eq!(a.shared_secret(&group, &b.public), BigUint::from_u64(36));
+    assert_eq!(b.shared_secret(&group, &a.public), BigUint::from_u64(36));
+
+    let group = Group::modp_1536();
+    let a = group.keypair(&mut rng);
+    let b = group.keypair(&mut rng);
+    let s = a.shared_secret(&group, &b.public);
+    assert_eq!(s, b.shared_secret(&group, &a.public));
+    assert_eq!(derive_key(&s), derive_key(&b.shared_secret(&group, &a.public)));
+    assert_eq!(derive_key(&s).len(), 16);
+  }
+
+  #[test]
+  fn test_groups() {
+    // All MODP groups are safe primes with generator 2.
+    let one = BigUint::one();
+    let groups = vec!(Group::modp_1536(), Group::modp_2048(), Group::modp_3072(), Group::modp_4096());
+
+    for (group, bits) in groups.iter().zip(vec!(1536, 2048, 3072, 4096)) {
+      assert_eq!(group.p.bits(), bits);
+
+      // 2 generates the subgroup of prime order q = (p - 1) / 2.
+      let q = (&group.p - &one) >> 1;
+      assert_eq!(group.g.modpow(&q, &group.p), one);
+    }
+  }
+
+  #[test]
+  fn test_random_below() {
+    let mut rng = OsRng::new().unwrap();
+    let bound = BigUint::from_u64(1000);
+
+    for _ in 0..1000 {
+      assert!(random_below(&mut rng, &bound) < bound);
+    }
+  }
+
+  #[test]
+  fn test_derive_key() {
+    // SHA1 of a single zero byte.
+    assert_eq!(derive_key(&BigUint::zero()), vec!(0x5b, 0xa9, 0x3c, 0x9d, 0xb0, 0xcf, 0xf9, 0x3f, 0x52, 0xb5, 0x21, 0xd7, 0x42, 0x0e, 0x43, 0xf6));
+  }
+}
@@ -0,0 +1,298 @@
+extern crate rand;
+extern crate bignum;
+extern crate challenge9;
+extern crate challenge10;
+extern crate challenge15;
+extern crate challenge33;
+
+use bignum::BigUint;
+use rand::{Rng, OsRng};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+use challenge9::PKCS7Pad;
+use challenge10::{aes_128_cbc_encrypt, aes_128_cbc_decrypt};
+use challenge15::PKCS7Unpad;
+use challenge33::{Group, KeyPair, derive_key};
+
+pub enum Message {
+  // p, g and A
+  Hello(BigUint, BigUint, BigUint),
+  // B
+  Reply(BigUint),
+  // AES-CBC(SHA1(s)[0:16], iv=random(16), msg) + iv
+  Data(Vec<u8>)
+}
+
+// One end of a bidirectional connection.
+pub struct Endpoint<M> {
+  tx: Sender<M>,
+  rx: Receiver<M>
+}
+
+impl<M: Send> Endpoint<M> {
+  // Two connected endpoints.
+  pub fn pair() -> (Endpoint<M>, Endpoint<M>) {
+    let (tx_a, rx_b) = channel();
+    let (tx_b, rx_a) = channel();
+    (Endpoint { tx: tx_a, rx: rx_a }, Endpoint { tx: tx_b, rx: rx_b })
+  }
+
+  // Returns false if the other end hung up.
+  pub fn send(&self, message: M) -> bool {
+    self.tx.send(message).is_ok()
+  }
+
+  pub fn recv(&self) -> Option<M> {
+    self.rx.recv().ok()
+  }
+}
+
+pub fn encrypt_message(key: &[u8], message: &[u8]) -> Vec<u8> {
+  let mut rng = OsRng::new().unwrap();
+  let mut iv = [0u8; 16];
+  rng.fill_bytes(&mut iv);
+
+  let mut data = aes_128_cbc_encrypt(key, &message.pkcs7_pad(16), iv.to_vec());
+  data.extend(iv.iter().cloned());
+  data
+}
+
+// Returns None if the padding is invalid, e.g. because of a wrong key.
+pub fn decrypt_message(key: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+  if data.len() < 32 || data.len() % 16 != 0 {
+    return None;
+  }
+
+  let (ciphertext, iv) = data.split_at(data.len() - 16);
+  aes_128_cbc_decrypt(key, ciphertext, iv.to_vec()).pkcs7_unpad()
+}
+
+// The messages of a key exchange followed by encrypted data. The key
+// exchange differs between protocols, the rest doesn't.
+pub trait Protocol: Send + Sized + 'static {
+  // A's side of the key exchange. Returns the key, or None if B hung up or
+  // sent something unexpected.
+  fn exchange_a(endpoint: &Endpoint<Self>, group: Group, private: &Option<BigUint>) -> Option<Vec<u8>>;
+  // B's side of the key exchange.
+  fn exchange_b(endpoint: &Endpoint<Self>, private: &Option<BigUint>) -> Option<Vec<u8>>;
+
+  fn data(data: Vec<u8>) -> Self;
+  fn into_data(self) -> Option<Vec<u8>>;
+}
+
+// Uses |private| as the private key, or picks a random one.
+pub fn keypair(group: &Group, private: &Option<BigUint>) -> KeyPair {
+  match *private {
+    Some(ref private) => group.keypair_from(private.clone()),
+    None => group.keypair(&mut OsRng::new().unwrap())
+  }
+}
+
+impl Protocol for Message {
+  // A sends |group| and its public key.
+  fn exchange_a(endpoint: &Endpoint<Message>, group: Group, private: &Option<BigUint>) -> Option<Vec<u8>> {
+    let keypair = keypair(&group, private);
+    endpoint.send(Message::Hello(group.p.clone(), group.g.clone(), keypair.public.clone()));
+
+    match endpoint.recv() {
+      Some(Message::Reply(b)) => Some(derive_key(&keypair.shared_secret(&group, &b))),
+      _ => None
+    }
+  }
+
+  // B accepts the group A sends.
+  fn exchange_b(endpoint: &Endpoint<Message>, private: &Option<BigUint>) -> Option<Vec<u8>> {
+    match endpoint.recv() {
+      Some(Message::Hello(p, g, a)) => {
+        let group = Group::new(p, g);
+        let keypair = keypair(&group, private);
+        endpoint.send(Message::Reply(keypair.public.clone()));
+        Some(derive_key(&keypair.shared_secret(&group, &a)))
+      }
+      _ => None
+    }
+  }
+
+  fn data(data: Vec<u8>) -> Message {
+    Message::Data(data)
+  }
+
+  fn into_data(self) -> Option<Vec<u8>> {
+    match self {
+      Message::Data(data) => Some(data),
+      _ => None
+    }
+  }
+}
+
+// A exchanges keys, using |private| if given, then sends every message in
+// |messages|. Returns what B echoed, as far as A could decrypt it.
+pub fn run_a<M: Protocol>(endpoint: Endpoint<M>, group: Group, private: Option<BigUint>, messages: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+  let key = match M::exchange_a(&endpoint, group, &private) {
+    Some(key) => key,
+    None => return vec!()
+  };
+
+  let mut echoes = vec!();
+  for message in messages {
+    endpoint.send(M::data(encrypt_message(&key, &message)));
+
+    match endpoint.recv().and_then(M::into_data) {
+      Some(data) => echoes.extend(decrypt_message(&key, &data)),
+      None => break
+    }
+  }
+
+  echoes
+}
+
+// B exchanges keys, using |private| if given, and echoes every message,
+// re-encrypted with a fresh IV. Runs until A hangs up, or hangs up itself
+// on a message it can't decrypt, so that A doesn't wait for a reply.
+pub fn run_b<M: Protocol>(endpoint: Endpoint<M>, private: Option<BigUint>) {
+  let key = match M::exchange_b(&endpoint, &private) {
+    Some(key) => key,
+    None => return
+  };
+
+  while let Some(data) = endpoint.recv().and_then(M::into_data) {
+    match decrypt_message(&key, &data) {
+      Some(message) => endpoint.send(M::data(encrypt_message(&key, &message))),
+      None => return
+    };
+  }
+}
+
+// A talks to B directly.
+pub fn simulate(group: Group, messages: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+  let (a, b) = Endpoint::pair();
+  let bob = thread::spawn(move || run_b::<Message>(b, None));
+  let echoes = run_a(a, group, None, messages);
+  bob.join().unwrap();
+  echoes
+}
+
+// Sits between A and B, and replaces both public keys with p. Both parties
+// compute s = p^x mod p = 0, so we know the key. Returns every message we
+// relayed, in order.
+pub fn parameter_injection(a: Endpoint<Message>, b: Endpoint<Message>) -> Vec<Vec<u8>> {
+  let p = match a.recv() {
+    Some(Message::Hello(p, g, _)) => {
+      b.send(Message::Hello(p.clone(), g, p.clone()));
+      p
+    }
+    _ => return vec!()
+  };
+
+  match b.recv() {
+    Some(Message::Reply(_)) => a.send(Message::Reply(p)),
+    _ => return vec!()
+  };
+
+  relay_data(&a, &b, &[derive_key(&BigUint::zero())])
+}
+
+// Forwards messages from A to B and back, until either hangs up. Decrypts
+// every message with the first of |keys| that yields valid padding.
+pub fn relay_data<M: Protocol>(a: &Endpoint<M>, b: &Endpoint<M>, keys: &[Vec<u8>]) -> Vec<Vec<u8>> {
+  let mut intercepted = vec!();
+
+  let mut relay = |from: &Endpoint<M>, to: &Endpoint<M>| {
+    match from.recv().and_then(M::into_data) {
+      Some(data) => {
+        intercepted.extend(keys.iter().filter_map(|key| decrypt_message(key, &data)).take(1));
+        to.send(M::data(data))
+      }
+      None => false
+    }
+  };
+
+  while relay(a, b) && relay(b, a) {}
+
+  intercepted
+}
+
+// A talks to B through |mitm|, which returns what it read.
+pub fn simulate_mitm<M, F>(group: Group, messages: Vec<Vec<u8>>, mitm: F) -> (Vec<Vec<u8>>, Vec<Vec<u8>>)
+    where M: Protocol, F: FnOnce(Endpoint<M>, Endpoint<M>) -> Vec<Vec<u8>> + Send + 'static
+{
+  simulate_mitm_with_keys(group, None, None, messages, mitm)
+}
+
+// Like simulate_mitm(), but A and B use the private keys |a| and |b|, if
+// given.
+pub fn simulate_mitm_with_keys<M, F>(group: Group, a: Option<BigUint>, b: Option<BigUint>, messages: Vec<Vec<u8>>, mitm: F) -> (Vec<Vec<u8>>, Vec<Vec<u8>>)
+    where M: Protocol, F: FnOnce(Endpoint<M>, Endpoint<M>) -> Vec<Vec<u8>> + Send + 'static
+{
+  let (endpoint_a, mitm_a) = Endpoint::pair();
+  let (mitm_b, endpoint_b) = Endpoint::pair();
+
+  let bob = thread::spawn(move || run_b(endpoint_b, b));
+  let relay = thread::spawn(move || mitm(mitm_a, mitm_b));
+
+  let echoes = run_a(endpoint_a, group, a, messages);
+  let intercepted = relay.join().unwrap();
+  bob.join().unwrap();
+
+  (echoes, intercepted)
+}
+
+#[cfg(test)]
+mod test {
+  use std::iter::FromIterator;
+  use std::thread;
+  use challenge33::Group;
+  use decrypt_message;
+  use encrypt_message;
+  use parameter_injection;
+  use run_b;
+  use simulate;
+  use simulate_mitm;
+  use Endpoint;
+  use Message;
+  use Protocol;
+
+  fn messages() -> Vec<Vec<u8>> {
+    Vec::from_iter(vec!("hello bob", "", "attack at dawn, bring exactly sixteen").iter().map(|m| m.as_bytes().to_vec()))
+  }
+
+  #[test]
+  fn test() {
+    assert_eq!(simulate(Group::modp_1536(), messages()), messages());
+    assert_eq!(simulate(Group::small(), messages()), messages());
+  }
+
+  #[test]
+  fn test_encryption() {
+    let key = b"YELLOW SUBMARINE";
+    let data = encrypt_message(key, b"sixteen byte msg");
+    assert_eq!(data.len(), 48);
+    assert_eq!(decrypt_message(key, &data), Some(b"sixteen byte msg".to_vec()));
+    assert!(encrypt_message(key, b"sixteen byte msg") != data);
+    assert_eq!(decrypt_message(key, &data[..16]), None);
+  }
+
+  #[test]
+  fn test_undecryptable() {
+    let (a, b) = Endpoint::pair();
+    let bob = thread::spawn(move || run_b::<Message>(b, None));
+
+    // B hangs up instead of leaving us waiting for a reply.
+    assert!(Message::exchange_a(&a, Group::small(), &None).is_some());
+    a.send(Message::Data(vec!(0; 20)));
+    assert!(a.recv().is_none());
+    bob.join().unwrap();
+  }
+
+  #[test]
+  fn test_parameter_injection() {
+    let (echoes, intercepted) = simulate_mitm(Group::modp_1536(), messages(), parameter_injection);
+
+    // Neither A nor B notice anything.
+    assert_eq!(echoes, messages());
+
+    // Every message went past us twice, once per direction.
+    let expected = Vec::from_iter(messages().into_iter().flat_map(|m| vec!(m.clone(), m).into_iter()));
+    assert_eq!(intercepted, expected);
+  }
+}
@@ -0,0 +1,160 @@
+extern crate bignum;
+extern crate challenge33;
+extern crate challenge34;
+
+use bignum::BigUint;
+use challenge33::{Group, derive_key};
+use challenge34::{Endpoint, Protocol, keypair, relay_data};
+
+pub enum Message {
+  // p and g
+  Negotiate(BigUint, BigUint),
+  // The group B accepted, A uses it too.
+  Ack(BigUint, BigUint),
+  // A or B
+  Public(BigUint),
+  Data(Vec<u8>)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaliciousG {
+  One,
+  P,
+  PMinusOne
+}
+
+impl Protocol for Message {
+  // A proposes |group|, then uses what B acknowledged.
+  fn exchange_a(endpoint: &Endpoint<Message>, group: Group, private: &Option<BigUint>) -> Option<Vec<u8>> {
+    endpoint.send(Message::Negotiate(group.p, group.g));
+
+    let group = match endpoint.recv() {
+      Some(Message::Ack(p, g)) => Group::new(p, g),
+      _ => return None
+    };
+
+    let keypair = keypair(&group, private);
+    endpoint.send(Message::Public(keypair.public.clone()));
+
+    match endpoint.recv() {
+      Some(Message::Public(b)) => Some(derive_key(&keypair.shared_secret(&group, &b))),
+      _ => None
+    }
+  }
+
+  // B acknowledges any group.
+  fn exchange_b(endpoint: &Endpoint<Message>, private: &Option<BigUint>) -> Option<Vec<u8>> {
+    let group = match endpoint.recv() {
+      Some(Message::Negotiate(p, g)) => {
+        endpoint.send(Message::Ack(p.clone(), g.clone()));
+        Group::new(p, g)
+      }
+      _ => return None
+    };
+
+    match endpoint.recv() {
+      Some(Message::Public(a)) => {
+        let keypair = keypair(&group, private);
+        endpoint.send(Message::Public(keypair.public.clone()));
+        Some(derive_key(&keypair.shared_secret(&group, &a)))
+      }
+      _ => None
+    }
+  }
+
+  fn data(data: Vec<u8>) -> Message {
+    Message::Data(data)
+  }
+
+  fn into_data(self) -> Option<Vec<u8>> {
+    match self {
+      Message::Data(data) => Some(data),
+      _ => None
+    }
+  }
+}
+
+// Replaces g in A's proposal. B acknowledges the malicious group and A
+// goes along with it, so both public keys are powers of the malicious g:
+//
+//   g = 1     =>  s = 1
+//   g = p     =>  s = 0
+//   g = p - 1 =>  s = p - 1 if both private keys are odd, 1 otherwise
+//
+// The public keys tell us which case we're in. Returns every message we
+// relayed, in order.
+pub fn malicious_g(a: Endpoint<Message>, b: Endpoint<Message>, attack: MaliciousG) -> Vec<Vec<u8>> {
+  let one = BigUint::one();
+
+  let p = match a.recv() {
+    Some(Message::Negotiate(p, _)) => p,
+    _ => return vec!()
+  };
+
+  let g = match attack {
+    MaliciousG::One => one.clone(),
+    MaliciousG::P => p.clone(),
+    MaliciousG::PMinusOne => &p - &one
+  };
+
+  b.send(Message::Negotiate(p.clone(), g));
+
+  // Relay the acknowledgement and both public keys.
+  let ack = b.recv();
+  a.send(match ack { Some(msg) => msg, None => return vec!() });
+
+  let pub_a = match a.recv() {
+    Some(Message::Public(pub_a)) => pub_a,
+    _ => return vec!()
+  };
+  b.send(Message::Public(pub_a.clone()));
+
+  let pub_b = match b.recv() {
+    Some(Message::Public(pub_b)) => pub_b,
+    _ => return vec!()
+  };
+  a.send(Message::Public(pub_b.clone()));
+
+  let secret = match attack {
+    MaliciousG::One => one,
+    MaliciousG::P => BigUint::zero(),
+    MaliciousG::PMinusOne if pub_a == &p - &one && pub_b == pub_a => pub_a,
+    MaliciousG::PMinusOne => one
+  };
+
+  relay_data(&a, &b, &[derive_key(&secret)])
+}
+
+#[cfg(test)]
+mod test {
+  use bignum::BigUint;
+  use std::iter::FromIterator;
+  use challenge33::Group;
+  use challenge34::simulate_mitm_with_keys;
+  use malicious_g;
+  use MaliciousG;
+
+  #[test]
+  fn test() {
+    let messages = Vec::from_iter(vec!("hello bob", "meet me at noon").iter().map(|m| m.as_bytes().to_vec()));
+    let expected = Vec::from_iter(messages.iter().flat_map(|m| vec!(m.clone(), m.clone()).into_iter()));
+
+    // g = p - 1 yields s = p - 1 only if both private keys are odd, so try
+    // both cases with fixed keys.
+    let cases = vec!(
+      (MaliciousG::One, None, None),
+      (MaliciousG::P, None, None),
+      (MaliciousG::PMinusOne, Some(3), Some(5)),
+      (MaliciousG::PMinusOne, Some(3), Some(4)),
+      (MaliciousG::PMinusOne, Some(6), Some(4))
+    );
+
+    for (attack, a, b) in cases {
+      let (a, b) = (a.map(BigUint::from_u64), b.map(BigUint::from_u64));
+      let (echoes, intercepted) = simulate_mitm_with_keys(Group::modp_1536(), a, b, messages.clone(),
+                                                          move |a, b| malicious_g(a, b, attack));
+      assert_eq!(echoes, messages);
+      assert_eq!(intercepted, expected);
+    }
+  }
+}
@@ -0,0 +1,270 @@
+extern crate rand;
+extern crate openssl;
+extern crate bignum;
+extern crate challenge33;
+extern crate challenge34;
+
+use bignum::BigUint;
+use openssl::crypto::hash::{hash, Type};
+use openssl::crypto::hmac::hmac;
+use rand::{Rng, OsRng};
+use std::iter::repeat;
+use std::thread;
+use challenge33::{Group, random_below};
+use challenge34::Endpoint;
+
+pub enum Message {
+  // I and A
+  Hello(String, BigUint),
+  // salt and B
+  Challenge(Vec<u8>, BigUint),
+  // HMAC-SHA256(K, salt)
+  Proof(Vec<u8>),
+  // Whether the server accepted the proof.
+  Result(bool)
+}
+
+pub fn sha256(parts: &[&[u8]]) -> Vec<u8> {
+  let mut data = vec!();
+  for part in parts {
+    data.extend(part.iter().cloned());
+  }
+
+  hash(Type::SHA256, &data)
+}
+
+// The multiplier parameter k = H(N | g).
+pub fn multiplier(group: &Group) -> BigUint {
+  BigUint::from_bytes_be(&sha256(&[&group.p.to_bytes_be(), &group.g.to_bytes_be()]))
+}
+
+// The random scrambling parameter u = H(A | B).
+pub fn scrambler(a: &BigUint, b: &BigUint) -> BigUint {
+  BigUint::from_bytes_be(&sha256(&[&a.to_bytes_be(), &b.to_bytes_be()]))
+}
+
+// The private key x = H(salt | H(I | ":" | P)).
+pub fn private_key(salt: &[u8], email: &str, password: &str) -> BigUint {
+  let inner = sha256(&[email.as_bytes(), b":", password.as_bytes()]);
+  BigUint::from_bytes_be(&sha256(&[salt, &inner]))
+}
+
+// The session key K = H(S), and what the client sends to prove it knows K.
+pub fn proof(secret: &BigUint, salt: &[u8]) -> Vec<u8> {
+  let key = sha256(&[&secret.to_bytes_be()]);
+  hmac(Type::SHA256, &key, salt)
+}
+
+// Picks a random salt and computes the verifier v = g^x mod N, which is
+// all a server stores about a user.
+pub fn register(group: &Group, email: &str, password: &str) -> (Vec<u8>, BigUint) {
+  let mut rng = OsRng::new().unwrap();
+  let mut salt = vec!(0u8; 16);
+  rng.fill_bytes(&mut salt);
+
+  let x = private_key(&salt, email, password);
+  (salt, group.g.modpow(&x, &group.p))
+}
+
+// Handles login attempts on one end of a connection.
+pub trait LoginServer<M>: Clone + Send + 'static {
+  // Handles a single login attempt. Returns whether the client proved that
+  // it knows the password.
+  fn run(&self, endpoint: Endpoint<M>) -> bool;
+}
+
+// The server knows a single user, and stores only the salt and the
+// verifier v = g^x mod N.
+#[derive(Clone)]
+pub struct Server {
+  group: Group,
+  email: String,
+  salt: Vec<u8>,
+  verifier: BigUint,
+  strict: bool
+}
+
+impl Server {
+  // A server that doesn't check the client's public key. This is what the
+  // challenge asks for.
+  pub fn new(group: &Group, email: &str, password: &str) -> Server {
+    let (salt, verifier) = register(group, email, password);
+
+    Server {
+      group: group.clone(),
+      email: email.to_string(),
+      salt: salt,
+      verifier: verifier,
+      strict: false
+    }
+  }
+
+  // A server that aborts when A mod N = 0, as required by RFC 5054.
+  pub fn strict(group: &Group, email: &str, password: &str) -> Server {
+    Server { strict: true, ..Server::new(group, email, password) }
+  }
+}
+
+impl LoginServer<Message> for Server {
+  fn run(&self, endpoint: Endpoint<Message>) -> bool {
+    let mut rng = OsRng::new().unwrap();
+    let n = &self.group.p;
+
+    let a = match endpoint.recv() {
+      Some(Message::Hello(ref email, ref a)) if *email == self.email => a.clone(),
+      Some(Message::Hello(..)) => {
+        endpoint.send(Message::Result(false));
+        return false;
+      }
+      _ => return false
+    };
+
+    if self.strict && (&a % n).is_zero() {
+      endpoint.send(Message::Result(false));
+      return false;
+    }
+
+    // B = k * v + g^b mod N. Clients abort on B = 0, which small groups
+    // hit every now and then, so pick another b.
+    let kv = &(&multiplier(&self.group) * &self.verifier) % n;
+    let (private, b) = repeat(()).map(|_| {
+      let private = &random_below(&mut rng, &(n - &BigUint::one())) + &BigUint::one();
+      let b = &(&kv + &self.group.g.modpow(&private, n)) % n;
+      (private, b)
+    }).find(|&(_, ref b)| !b.is_zero()).unwrap();
+    endpoint.send(Message::Challenge(self.salt.clone(), b.clone()));
+
+    // S = (A * v^u)^b mod N
+    let u = scrambler(&a, &b);
+    let s = (&(&a * &self.verifier.modpow(&u, n)) % n).modpow(&private, n);
+
+    let valid = match endpoint.recv() {
+      Some(Message::Proof(mac)) => mac == proof(&s, &self.salt),
+      _ => return false
+    };
+
+    endpoint.send(Message::Result(valid));
+    valid
+  }
+}
+
+// Logs into the server at the other end of |endpoint|. Returns whether the
+// server accepted our proof.
+pub fn login(endpoint: Endpoint<Message>, group: &Group, email: &str, password: &str) -> bool {
+  let mut rng = OsRng::new().unwrap();
+  let n = &group.p;
+
+  let keypair = group.keypair(&mut rng);
+  endpoint.send(Message::Hello(email.to_string(), keypair.public.clone()));
+
+  let (salt, b) = match endpoint.recv() {
+    Some(Message::Challenge(salt, b)) => (salt, b),
+    _ => return false
+  };
+
+  // RFC 5054 requires aborting when B mod N = 0 or u = 0, a malicious
+  // server could otherwise force a known S. Hang up.
+  if (&b % n).is_zero() {
+    return false;
+  }
+
+  let u = scrambler(&keypair.public, &b);
+  if u.is_zero() {
+    return false;
+  }
+
+  // S = (B - k * g^x)^(a + u * x) mod N
+  let x = private_key(&salt, email, password);
+  let kgx = &(&multiplier(group) * &group.g.modpow(&x, n)) % n;
+  let base = &(&(&b % n) + &(n - &kgx)) % n;
+  let s = base.modpow(&(&keypair.private + &(&u * &x)), n);
+
+  endpoint.send(Message::Proof(proof(&s, &salt)));
+
+  match endpoint.recv() {
+    Some(Message::Result(valid)) => valid,
+    _ => false
+  }
+}
+
+// Runs |client| against |server|, each in its own thread. Returns what the
+// client and the server think the outcome was.
+pub fn simulate<M, S, F>(server: &S, client: F) -> (bool, bool)
+    where M: Send + 'static, S: LoginServer<M>, F: FnOnce(Endpoint<M>) -> bool + Send + 'static
+{
+  let (c, s) = Endpoint::pair();
+  let server = server.clone();
+
+  let handle = thread::spawn(move || server.run(s));
+  let client = client(c);
+  (client, handle.join().unwrap())
+}
+
+#[cfg(test)]
+mod test {
+  use std::thread;
+  use challenge33::Group;
+  use challenge34::Endpoint;
+  use Message;
+  use Server;
+  use login;
+  use simulate;
+
+  #[test]
+  fn test() {
+    let group = Group::modp_1536();
+    let server = Server::new(&group, "alice@example.com", "correct horse battery staple");
+
+    let g = group.clone();
+    let result = simulate(&server, move |c| login(c, &g, "alice@example.com", "correct horse battery staple"));
+    assert_eq!(result, (true, true));
+
+    // A wrong password.
+    let g = group.clone();
+    let result = simulate(&server, move |c| login(c, &g, "alice@example.com", "correct horse battery stapler"));
+    assert_eq!(result, (false, false));
+
+    // An unknown user.
+    let g = group.clone();
+    let result = simulate(&server, move |c| login(c, &g, "mallory@example.com", "correct horse battery staple"));
+    assert_eq!(result, (false, false));
+  }
+
+  #[test]
+  fn test_strict() {
+    // Honest clients don't notice the checks.
+    let group = Group::modp_1536();
+    let server = Server::strict(&group, "alice@example.com", "hunter2");
+
+    let g = group.clone();
+    assert_eq!(simulate(&server, move |c| login(c, &g, "alice@example.com", "hunter2")), (true, true));
+  }
+
+  #[test]
+  fn test_small_group() {
+    let group = Group::small();
+    let server = Server::new(&group, "bob@example.com", "swordfish");
+
+    for _ in 0..10 {
+      let g = group.clone();
+      assert_eq!(simulate(&server, move |c| login(c, &g, "bob@example.com", "swordfish")), (true, true));
+    }
+  }
+
+  #[test]
+  fn test_malicious_server() {
+    let group = Group::modp_1536();
+
+    // B = N would make S = 0 for any password, the client hangs up.
+    for b in vec!(group.p.clone(), &group.p + &group.p) {
+      let (c, s) = Endpoint::pair();
+      let g = group.clone();
+      let handle = thread::spawn(move || login(c, &g, "alice@example.com", "hunter2"));
+
+      assert!(match s.recv() { Some(Message::Hello(..)) => true, _ => false });
+      s.send(Message::Challenge(vec!(0u8; 16), b));
+      assert!(s.recv().is_none());
+      assert_eq!(handle.join().unwrap(), false);
+    }
+  }
+}
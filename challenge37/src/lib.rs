@@ -0,0 +1,65 @@
+extern crate bignum;
+extern crate challenge34;
+extern crate challenge36;
+
+#[cfg(test)]
+extern crate challenge33;
+
+use bignum::BigUint;
+use challenge34::Endpoint;
+use challenge36::{Message, proof};
+
+// Logs in without knowing the password, by sending a multiple of N as A.
+// The server computes S = (A * v^u)^b mod N = 0, and so can we.
+pub fn zero_key_login(endpoint: Endpoint<Message>, email: &str, a: BigUint) -> bool {
+  endpoint.send(Message::Hello(email.to_string(), a));
+
+  let salt = match endpoint.recv() {
+    Some(Message::Challenge(salt, _)) => salt,
+    _ => return false
+  };
+
+  endpoint.send(Message::Proof(proof(&BigUint::zero(), &salt)));
+
+  match endpoint.recv() {
+    Some(Message::Result(valid)) => valid,
+    _ => false
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use bignum::BigUint;
+  use challenge33::Group;
+  use challenge36::{Server, simulate};
+  use zero_key_login;
+
+  fn multiples(group: &Group) -> Vec<BigUint> {
+    let n = &group.p;
+    vec!(BigUint::zero(), n.clone(), n + n, n * &BigUint::from_u64(1 << 40))
+  }
+
+  #[test]
+  fn test() {
+    let group = Group::modp_1536();
+    let server = Server::new(&group, "alice@example.com", "correct horse battery staple");
+
+    for a in multiples(&group) {
+      assert_eq!(simulate(&server, move |c| zero_key_login(c, "alice@example.com", a)), (true, true));
+    }
+  }
+
+  #[test]
+  fn test_strict() {
+    let group = Group::modp_1536();
+    let server = Server::strict(&group, "alice@example.com", "correct horse battery staple");
+
+    for a in multiples(&group) {
+      assert_eq!(simulate(&server, move |c| zero_key_login(c, "alice@example.com", a)), (false, false));
+    }
+
+    // Other values of A still need the password.
+    let a = BigUint::from_u64(2);
+    assert_eq!(simulate(&server, move |c| zero_key_login(c, "alice@example.com", a)), (false, false));
+  }
+}
@@ -0,0 +1,191 @@
+extern crate rand;
+extern crate bignum;
+extern crate challenge33;
+extern crate challenge34;
+extern crate challenge36;
+
+#[cfg(test)]
+extern crate challenge4;
+
+use bignum::BigUint;
+use rand::OsRng;
+use challenge33::{Group, random_below};
+use challenge34::Endpoint;
+use challenge36::{LoginServer, private_key, proof, register};
+
+pub enum Message {
+  // I and A
+  Hello(String, BigUint),
+  // salt, B and u
+  Challenge(Vec<u8>, BigUint, BigUint),
+  // HMAC-SHA256(K, salt)
+  Proof(Vec<u8>),
+  // Whether the server accepted the proof.
+  Result(bool)
+}
+
+// Simplified SRP. B = g^b doesn't depend on the password, and u is random
+// instead of H(A | B).
+#[derive(Clone)]
+pub struct Server {
+  group: Group,
+  email: String,
+  salt: Vec<u8>,
+  verifier: BigUint
+}
+
+impl Server {
+  pub fn new(group: &Group, email: &str, password: &str) -> Server {
+    let (salt, verifier) = register(group, email, password);
+    Server { group: group.clone(), email: email.to_string(), salt: salt, verifier: verifier }
+  }
+}
+
+impl LoginServer<Message> for Server {
+  fn run(&self, endpoint: Endpoint<Message>) -> bool {
+    let mut rng = OsRng::new().unwrap();
+    let n = &self.group.p;
+
+    let a = match endpoint.recv() {
+      Some(Message::Hello(ref email, ref a)) if *email == self.email => a.clone(),
+      Some(Message::Hello(..)) => {
+        endpoint.send(Message::Result(false));
+        return false;
+      }
+      _ => return false
+    };
+
+    let keypair = self.group.keypair(&mut rng);
+    let u = random_below(&mut rng, &(BigUint::one() << 128));
+    endpoint.send(Message::Challenge(self.salt.clone(), keypair.public.clone(), u.clone()));
+
+    // S = (A * v^u)^b mod N
+    let s = (&(&a * &self.verifier.modpow(&u, n)) % n).modpow(&keypair.private, n);
+
+    let valid = match endpoint.recv() {
+      Some(Message::Proof(mac)) => mac == proof(&s, &self.salt),
+      _ => return false
+    };
+
+    endpoint.send(Message::Result(valid));
+    valid
+  }
+}
+
+// Logs into the server at the other end of |endpoint|. Returns whether the
+// server accepted our proof.
+pub fn login(endpoint: Endpoint<Message>, group: &Group, email: &str, password: &str) -> bool {
+  let mut rng = OsRng::new().unwrap();
+  let keypair = group.keypair(&mut rng);
+  endpoint.send(Message::Hello(email.to_string(), keypair.public.clone()));
+
+  let (salt, b, u) = match endpoint.recv() {
+    Some(Message::Challenge(salt, b, u)) => (salt, b, u),
+    _ => return false
+  };
+
+  // S = B^(a + u * x) mod N
+  let x = private_key(&salt, email, password);
+  let s = b.modpow(&(&keypair.private + &(&u * &x)), &group.p);
+  endpoint.send(Message::Proof(proof(&s, &salt)));
+
+  match endpoint.recv() {
+    Some(Message::Result(valid)) => valid,
+    _ => false
+  }
+}
+
+// What we learned from a client that tried to log into fake_server().
+pub struct Captured {
+  pub email: String,
+  pub salt: Vec<u8>,
+  pub public: BigUint,
+  pub mac: Vec<u8>
+}
+
+// Poses as the server. We pick b = 1 and u = 1, so that the client computes
+// S = g^(a + x) = A * g^x mod N. Returns None if the client hung up early.
+//
+// The challenge describes a MITM, but this never talks to the real server.
+// The client's proof depends on our b and u, so the real server would
+// reject it anyway, and cracking the password doesn't need anything the
+// server knows. The client just sees a failed login.
+pub fn fake_server(endpoint: Endpoint<Message>, group: &Group) -> Option<Captured> {
+  let (email, a) = match endpoint.recv() {
+    Some(Message::Hello(email, a)) => (email, a),
+    _ => return None
+  };
+
+  let salt = vec!(0u8; 16);
+  endpoint.send(Message::Challenge(salt.clone(), group.g.clone(), BigUint::one()));
+
+  let mac = match endpoint.recv() {
+    Some(Message::Proof(mac)) => mac,
+    _ => return None
+  };
+
+  endpoint.send(Message::Result(false));
+  Some(Captured { email: email, salt: salt, public: a, mac: mac })
+}
+
+// Tries every password in |words| against what we captured, offline.
+pub fn crack_password<'a, I>(group: &Group, captured: &Captured, words: I) -> Option<String>
+    where I: Iterator<Item=&'a str>
+{
+  let n = &group.p;
+
+  for password in words {
+    let x = private_key(&captured.salt, &captured.email, password);
+    let s = &(&captured.public * &group.g.modpow(&x, n)) % n;
+
+    if proof(&s, &captured.salt) == captured.mac {
+      return Some(password.to_string());
+    }
+  }
+
+  None
+}
+
+#[cfg(test)]
+mod test {
+  use challenge4::read_file;
+  use challenge33::Group;
+  use challenge34::Endpoint;
+  use challenge36::simulate;
+  use std::thread;
+  use Server;
+  use crack_password;
+  use fake_server;
+  use login;
+
+  #[test]
+  fn test() {
+    let group = Group::modp_1536();
+    let server = Server::new(&group, "alice@example.com", "sunshine");
+
+    let g = group.clone();
+    assert_eq!(simulate(&server, move |c| login(c, &g, "alice@example.com", "sunshine")), (true, true));
+
+    let g = group.clone();
+    assert_eq!(simulate(&server, move |c| login(c, &g, "alice@example.com", "moonshine")), (false, false));
+  }
+
+  #[test]
+  fn test_dictionary_attack() {
+    let group = Group::modp_1536();
+    let words = read_file("words.txt");
+
+    for &(password, found) in [("sunshine", true), ("tr0ub4dor&3", false)].iter() {
+      let (client, server) = Endpoint::pair();
+      let g = group.clone();
+      let handle = thread::spawn(move || login(client, &g, "alice@example.com", password));
+
+      let captured = fake_server(server, &group).unwrap();
+      assert_eq!(handle.join().unwrap(), false);
+      assert_eq!(captured.email, "alice@example.com");
+
+      let cracked = crack_password(&group, &captured, words.lines());
+      assert_eq!(cracked, if found { Some(password.to_string()) } else { None });
+    }
+  }
+}
@@ -0,0 +1,337 @@
+extern crate rand;
+extern crate openssl;
+extern crate bignum;
+extern crate challenge33;
+
+use bignum::BigUint;
+use openssl::crypto::hash::Type;
+use openssl::crypto::hmac::hmac;
+use rand::{Rng, OsRng};
+use std::iter::FromIterator;
+use challenge33::{Group, random_below};
+
+static MESSAGE: &'static [u8] = b"crazy flamboyant for the rap enjoyment";
+
+// The primes below |bound|, with the sieve of Eratosthenes.
+pub fn small_primes(bound: u64) -> Vec<u64> {
+  let mut composite = vec!(false; bound as usize);
+
+  for i in 2..bound as usize {
+    if !composite[i] {
+      let mut j = i * i;
+      while j < bound as usize {
+        composite[j] = true;
+        j += i;
+      }
+    }
+  }
+
+  Vec::from_iter((2..bound).filter(|i| !composite[*i as usize]))
+}
+
+// Trial division by the first few primes, then |rounds| rounds of
+// Miller-Rabin with random bases.
+pub fn is_probable_prime<R: Rng>(rng: &mut R, n: &BigUint, rounds: usize) -> bool {
+  let one = BigUint::one();
+  let two = BigUint::from_u64(2);
+
+  for prime in small_primes(256) {
+    let prime = BigUint::from_u64(prime);
+    if *n == prime {
+      return true;
+    }
+    if (n % &prime).is_zero() {
+      return false;
+    }
+  }
+
+  if *n < two {
+    return false;
+  }
+
+  // n - 1 = d * 2^s
+  let n_minus_one = n - &one;
+  let s = (0..n_minus_one.bits()).take_while(|i| !n_minus_one.bit(*i)).count();
+  let d = n_minus_one.clone() >> s;
+
+  'witness: for _ in 0..rounds {
+    let a = &random_below(rng, &(n - &BigUint::from_u64(3))) + &two;
+    let mut x = a.modpow(&d, n);
+
+    if x == one || x == n_minus_one {
+      continue;
+    }
+
+    for _ in 1..s {
+      x = x.modpow(&two, n);
+      if x == n_minus_one {
+        continue 'witness;
+      }
+    }
+
+    return false;
+  }
+
+  true
+}
+
+// A random prime with exactly |bits| bits.
+pub fn random_prime<R: Rng>(rng: &mut R, bits: usize) -> BigUint {
+  let top = BigUint::one() << (bits - 1);
+
+  loop {
+    let candidate = &(&random_below(rng, &top) + &top) + &BigUint::one();
+    if is_probable_prime(rng, &candidate, 20) {
+      return candidate;
+    }
+  }
+}
+
+// Generates a group with p = q * j + 1 and a generator of order q. About
+// |smooth_bits| of j consist of distinct primes below 2^16, the rest is
+// random. p has about |p_bits| bits. Returns the group and q.
+pub fn generate_group<R: Rng>(rng: &mut R, q_bits: usize, smooth_bits: usize, p_bits: usize) -> (Group, BigUint) {
+  // The last small prime can overshoot |smooth_bits| by up to 16 bits.
+  assert!(q_bits + smooth_bits + 16 < p_bits);
+
+  let q = random_prime(rng, q_bits);
+  let primes = small_primes(1 << 16);
+  let one = BigUint::one();
+
+  loop {
+    // Start with 2, p - 1 must be even.
+    let mut j = BigUint::from_u64(2);
+    let mut used = vec!();
+
+    while j.bits() < smooth_bits {
+      let prime = primes[rng.gen_range(1, primes.len())];
+      if !used.contains(&prime) {
+        used.push(prime);
+        j = &j * &BigUint::from_u64(prime);
+      }
+    }
+
+    // Fill up with random bits, so that p has about |p_bits| bits.
+    let top = one.clone() << (p_bits - q_bits - j.bits());
+    let j = &j * &(&random_below(rng, &top) + &top);
+
+    let p = &(&q * &j) + &one;
+    if !is_probable_prime(rng, &p, 20) {
+      continue;
+    }
+
+    // Any h^j != 1 has order q.
+    loop {
+      let h = &random_below(rng, &(&p - &BigUint::from_u64(2))) + &BigUint::from_u64(2);
+      let g = h.modpow(&j, &p);
+      if g != one {
+        return (Group::new(p, g), q);
+      }
+    }
+  }
+}
+
+// Bob. He doesn't check whether the public keys he receives are in the
+// subgroup of order q, and uses them to MAC a message.
+pub struct BlackBox {
+  group: Group,
+  private: BigUint
+}
+
+impl BlackBox {
+  pub fn new(group: &Group, q: &BigUint) -> BlackBox {
+    let mut rng = OsRng::new().unwrap();
+    let private = &random_below(&mut rng, &(q - &BigUint::one())) + &BigUint::one();
+    BlackBox { group: group.clone(), private: private }
+  }
+
+  pub fn public(&self) -> BigUint {
+    self.group.g.modpow(&self.private, &self.group.p)
+  }
+
+  // Returns the message and its MAC, keyed with K = h^x mod p.
+  pub fn respond(&self, h: &BigUint) -> (Vec<u8>, Vec<u8>) {
+    let k = h.modpow(&self.private, &self.group.p);
+    (MESSAGE.to_vec(), mac(&k, MESSAGE))
+  }
+
+  pub fn is_private_key(&self, x: &BigUint) -> bool {
+    *x == self.private
+  }
+}
+
+pub fn mac(k: &BigUint, message: &[u8]) -> Vec<u8> {
+  hmac(Type::SHA256, &k.to_bytes_be(), message)
+}
+
+// The distinct prime factors of |n| below |bound|.
+pub fn small_factors(n: &BigUint, bound: u64) -> Vec<u64> {
+  Vec::from_iter(small_primes(bound).into_iter().filter(|r| (n % &BigUint::from_u64(*r)).is_zero()))
+}
+
+// A random element of order |r|, which must divide p - 1.
+pub fn element_of_order<R: Rng>(rng: &mut R, p: &BigUint, r: u64) -> BigUint {
+  let one = BigUint::one();
+  let exponent = &(p - &one) / &BigUint::from_u64(r);
+
+  loop {
+    let h = &random_below(rng, &(p - &one)) + &one;
+    let h = h.modpow(&exponent, p);
+    if h != one {
+      return h;
+    }
+  }
+}
+
+// Finds x mod r for an |h| of order |r|, by trying every K = h^x.
+pub fn brute_force_residue(h: &BigUint, r: u64, p: &BigUint, message: &[u8], tag: &[u8]) -> Option<u64> {
+  let mut k = BigUint::one();
+
+  for x in 0..r {
+    if mac(&k, message) == tag {
+      return Some(x);
+    }
+
+    k = &(&k * h) % p;
+  }
+
+  None
+}
+
+// Combines x = a_i mod r_i into x mod r_1 * ... * r_n. Returns x and the
+// product of all moduli, which must be pairwise coprime.
+pub fn crt(residues: &[(BigUint, BigUint)]) -> (BigUint, BigUint) {
+  let modulus = residues.iter().fold(BigUint::one(), |m, &(_, ref r)| &m * r);
+
+  let x = residues.iter().fold(BigUint::zero(), |x, &(ref a, ref r)| {
+    let m = &modulus / r;
+    let inv = (&m % r).modinv(r).expect("moduli must be coprime");
+    &(&x + &(&(a * &m) * &inv)) % &modulus
+  });
+
+  (x, modulus)
+}
+
+// Sends elements of small order r | (p - 1) / q to |respond| and learns the
+// private key mod r from every MAC. Stops once the product of all r exceeds
+// q, or when we run out of factors below |bound|. Returns x mod m and m.
+pub fn recover_residues<F>(group: &Group, q: &BigUint, respond: F, bound: u64) -> (BigUint, BigUint)
+    where F: Fn(&BigUint) -> (Vec<u8>, Vec<u8>)
+{
+  let mut rng = OsRng::new().unwrap();
+  let j = &(&group.p - &BigUint::one()) / q;
+  let mut residues = vec!();
+  let mut product = BigUint::one();
+
+  for r in small_factors(&j, bound) {
+    // Factors of q itself don't help.
+    if (q % &BigUint::from_u64(r)).is_zero() {
+      continue;
+    }
+
+    let h = element_of_order(&mut rng, &group.p, r);
+    let (message, tag) = respond(&h);
+
+    if let Some(x) = brute_force_residue(&h, r, &group.p, &message, &tag) {
+      residues.push((BigUint::from_u64(x), BigUint::from_u64(r)));
+      product = &product * &BigUint::from_u64(r);
+
+      if product > *q {
+        break;
+      }
+    }
+  }
+
+  crt(&residues)
+}
+
+// Pohlig-Hellman: recovers the whole private key if (p - 1) / q has enough
+// small factors below |bound|.
+pub fn recover_key<F>(group: &Group, q: &BigUint, respond: F, bound: u64) -> Option<BigUint>
+    where F: Fn(&BigUint) -> (Vec<u8>, Vec<u8>)
+{
+  let (x, modulus) = recover_residues(group, q, respond, bound);
+  if modulus > *q { Some(x) } else { None }
+}
+
+#[cfg(test)]
+mod test {
+  use bignum::BigUint;
+  use std::iter::FromIterator;
+  use challenge33::Group;
+  use rand::{OsRng, SeedableRng, StdRng};
+  use BlackBox;
+  use crt;
+  use generate_group;
+  use is_probable_prime;
+  use recover_key;
+  use small_factors;
+  use small_primes;
+
+  #[test]
+  fn test() {
+    // The parameters from the challenge.
+    let p = "7199773997391911030609999317773941274322764333428698921736339643928346453700085358802973900485592910475480089726140708102474957429903531369589969318716771".parse().unwrap();
+    let g = "4565356397095740655436854503483826832136106141639563487732438195343690437606117828318042418238184896212352329118608100083187535033402010599512641674644143".parse().unwrap();
+    let q = "236234353446506858198510045061214171961".parse().unwrap();
+    let group = Group::new(p, g);
+
+    let blackbox = BlackBox::new(&group, &q);
+    let x = recover_key(&group, &q, |h| blackbox.respond(h), 1 << 16).unwrap();
+    assert!(blackbox.is_private_key(&x));
+    assert_eq!(group.g.modpow(&x, &group.p), blackbox.public());
+  }
+
+  #[test]
+  fn test_generated_groups() {
+    let mut rng = StdRng::from_seed(&[57][..]);
+
+    for &(q_bits, p_bits) in [(40, 256), (64, 384)].iter() {
+      let (group, q) = generate_group(&mut rng, q_bits, q_bits + 16, p_bits);
+      assert!(is_probable_prime(&mut rng, &group.p, 20));
+      assert_eq!(group.g.modpow(&q, &group.p), BigUint::one());
+
+      let blackbox = BlackBox::new(&group, &q);
+      let x = recover_key(&group, &q, |h| blackbox.respond(h), 1 << 16).unwrap();
+      assert!(blackbox.is_private_key(&x));
+    }
+  }
+
+  #[test]
+  fn test_not_enough_factors() {
+    let mut rng = StdRng::from_seed(&[58][..]);
+    let (group, q) = generate_group(&mut rng, 64, 24, 256);
+
+    let blackbox = BlackBox::new(&group, &q);
+    assert_eq!(recover_key(&group, &q, |h| blackbox.respond(h), 1 << 16), None);
+  }
+
+  #[test]
+  fn test_primes() {
+    let mut rng = OsRng::new().unwrap();
+    assert_eq!(small_primes(30), vec!(2, 3, 5, 7, 11, 13, 17, 19, 23, 29));
+    assert_eq!(small_primes(1 << 16).len(), 6542);
+
+    // 2^127 - 1 is a Mersenne prime, 2^128 + 1 isn't a prime.
+    let m127 = &(BigUint::one() << 127) - &BigUint::one();
+    assert!(is_probable_prime(&mut rng, &m127, 20));
+    assert!(!is_probable_prime(&mut rng, &(&(BigUint::one() << 128) + &BigUint::one()), 20));
+
+    // A Carmichael number.
+    assert!(!is_probable_prime(&mut rng, &BigUint::from_u64(561), 20));
+
+    // A strong pseudoprime to base 2, 277 * 1013, that gets past trial
+    // division and has to be caught by Miller-Rabin.
+    assert!(!is_probable_prime(&mut rng, &BigUint::from_u64(280601), 20));
+  }
+
+  #[test]
+  fn test_crt() {
+    let residues = vec!((2, 3), (3, 5), (2, 7));
+    let residues = Vec::from_iter(residues.iter().map(|&(a, r)| (BigUint::from_u64(a), BigUint::from_u64(r))));
+    assert_eq!(crt(&residues), (BigUint::from_u64(23), BigUint::from_u64(105)));
+
+    let n = BigUint::from_u64(2 * 3 * 3 * 101 * 65521);
+    assert_eq!(small_factors(&n, 1 << 16), vec!(2, 3, 101, 65521));
+  }
+}
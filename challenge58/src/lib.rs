@@ -0,0 +1,141 @@
+extern crate rand;
+extern crate bignum;
+extern crate challenge33;
+extern crate challenge57;
+
+use bignum::BigUint;
+use challenge33::Group;
+use challenge57::recover_residues;
+
+// The pseudo-random jump for |y|, as an index into the table of jumps.
+fn jump_index(y: &BigUint, k: u64) -> usize {
+  (y % &BigUint::from_u64(k)).to_u64().unwrap() as usize
+}
+
+// One try at catching the wild kangaroo, with jumps of size 2^0 .. 2^(k-1).
+fn kangaroo_with(g: &BigUint, y: &BigUint, p: &BigUint, a: u64, b: u64, k: u64) -> Option<u64> {
+  // Precompute g^(2^i) for every possible jump.
+  let mut jumps = vec!(g.clone());
+  for i in 1..k as usize {
+    let next = &(&jumps[i - 1] * &jumps[i - 1]) % p;
+    jumps.push(next);
+  }
+
+  // Let the tame kangaroo make N = 4 * mean jump size jumps, starting at b.
+  let n = 4 * ((1u64 << k) - 1) / k;
+  let mut x_tame = 0u64;
+  let mut y_tame = g.modpow(&BigUint::from_u64(b), p);
+
+  for _ in 0..n {
+    let index = jump_index(&y_tame, k);
+    x_tame += 1 << index;
+    y_tame = &(&y_tame * &jumps[index]) % p;
+  }
+
+  // The wild kangaroo starts at y and either lands on the tame kangaroo's
+  // trap, or passes it.
+  let mut x_wild = 0u64;
+  let mut y_wild = y.clone();
+
+  while x_wild <= b - a + x_tame {
+    if y_wild == y_tame {
+      return Some(b + x_tame - x_wild);
+    }
+
+    let index = jump_index(&y_wild, k);
+    x_wild += 1 << index;
+    y_wild = &(&y_wild * &jumps[index]) % p;
+  }
+
+  None
+}
+
+// Pollard's kangaroo algorithm. Finds x in [a, b] with y = g^x mod p, or
+// returns None if there is none. The interval must be narrower than 2^56.
+pub fn kangaroo(g: &BigUint, y: &BigUint, p: &BigUint, a: u64, b: u64) -> Option<u64> {
+  assert!(a <= b && b - a < 1 << 56);
+
+  // Pick k such that the mean jump size is about sqrt(b - a) / 2.
+  let half = (64 - (b - a).leading_zeros() as u64 + 1) / 2;
+  let log = 64 - half.leading_zeros() as u64;
+  let k = if half + log > 3 { half + log - 2 } else { 1 };
+
+  // The walk is deterministic, if the wild kangaroo misses the trap we need
+  // to try again with different jumps. Distances still fit into 64 bits.
+  for k in k..k + 3 {
+    if let Some(x) = kangaroo_with(g, y, p, a, b, k) {
+      return Some(x);
+    }
+  }
+
+  None
+}
+
+// Learns x mod r via small subgroup confinement, then finds the rest of the
+// private key with the kangaroo algorithm. x = n + m * r, so we're looking
+// for m in [0, (q - 1) / r] with y * g^-n = (g^r)^m.
+pub fn recover_key<F>(group: &Group, q: &BigUint, public: &BigUint, respond: F, bound: u64) -> Option<BigUint>
+    where F: Fn(&BigUint) -> (Vec<u8>, Vec<u8>)
+{
+  let (n, r) = recover_residues(group, q, respond, bound);
+  if r > *q {
+    return Some(n);
+  }
+
+  let width = match (&(q - &BigUint::one()) / &r).to_u64() {
+    Some(width) if width < 1 << 56 => width,
+    _ => return None
+  };
+
+  // g has order q, so g^-n = g^(q - n).
+  let y = &(public * &group.g.modpow(&(q - &n), &group.p)) % &group.p;
+  let g = group.g.modpow(&r, &group.p);
+
+  kangaroo(&g, &y, &group.p, 0, width).map(|m| &n + &(&BigUint::from_u64(m) * &r))
+}
+
+#[cfg(test)]
+mod test {
+  use bignum::BigUint;
+  use challenge33::Group;
+  use challenge57::{BlackBox, generate_group};
+  use rand::{SeedableRng, StdRng};
+  use kangaroo;
+  use recover_key;
+
+  fn challenge_group() -> Group {
+    let p = "11470374874925275658116663507232161402086650258453896274534991676898999262641581519101074740642369848233294239851519212341844337347119899874391456329785623".parse().unwrap();
+    let g = "622952335333961296978159266084741085889881358738459939978290179936063635566740258555167783009058567397963466103140082647486611657350811560630587013183357".parse().unwrap();
+    Group::new(p, g)
+  }
+
+  #[test]
+  fn test_kangaroo() {
+    let group = challenge_group();
+
+    let y = "7760073848032689505395005705677365876654629189298052775754597607446617558600394076764814236081991643094239886772481052254010323780165093955236429914607119".parse().unwrap();
+    let x = kangaroo(&group.g, &y, &group.p, 0, 1 << 20).unwrap();
+    assert_eq!(group.g.modpow(&BigUint::from_u64(x), &group.p), y);
+
+    // Intervals that don't start at zero.
+    for &x in [1234567u64, 1 << 24, (1 << 25) - 1].iter() {
+      let y = group.g.modpow(&BigUint::from_u64(x), &group.p);
+      assert_eq!(kangaroo(&group.g, &y, &group.p, 1 << 24, 1 << 25), if x >= 1 << 24 { Some(x) } else { None });
+    }
+  }
+
+  #[test]
+  fn test() {
+    let mut rng = StdRng::from_seed(&[58][..]);
+
+    // (p - 1) / q doesn't have enough small factors to recover the whole
+    // key, about 24 bits remain for the kangaroos.
+    for &(q_bits, p_bits) in [(64, 256), (80, 384)].iter() {
+      let (group, q) = generate_group(&mut rng, q_bits, q_bits - 24, p_bits);
+
+      let blackbox = BlackBox::new(&group, &q);
+      let x = recover_key(&group, &q, &blackbox.public(), |h| blackbox.respond(h), 1 << 16).unwrap();
+      assert!(blackbox.is_private_key(&x));
+    }
+  }
+}
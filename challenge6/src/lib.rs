@@ -60,7 +60,9 @@ impl Ord for CandidateDecryption {
   }
 }
 
-fn rank_xor_keysizes(bytes: &[u8]) -> Vec<usize> {
+// Ranks key sizes 2..41 by the Hamming distance of the first three blocks,
+// more likely ones first. Needs at least 120 bytes.
+pub fn rank_xor_keysizes(bytes: &[u8]) -> Vec<usize> {
   assert!(bytes.len() >= 3 * 40);
   let mut heap = BinaryHeap::with_capacity(39);
 
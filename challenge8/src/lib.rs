@@ -1,12 +1,32 @@
+extern crate challenge1;
 extern crate challenge4;
+extern crate challenge6;
 
-use std::collections::HashSet;
+#[cfg(test)]
+extern crate rand;
 
-pub fn find_ecb_ciphertext<'a>(ciphertexts: &[&'a str]) -> &'a str {
-  match ciphertexts.iter().find(|ct| is_ecb_ciphertext(ct.as_bytes(), 32)) {
-    Some(ct) => ct,
-    None => ""
-  }
+use std::collections::{HashMap, HashSet};
+use std::iter::FromIterator;
+use challenge1::HexDecoder;
+use challenge6::{HammingDistance, rank_xor_keysizes};
+
+// Block sizes we consider, the most common one first.
+const BLOCKSIZES: [usize; 3] = [16, 8, 32];
+
+// Random data differs in about 4 bits per byte. Text XORed with a repeated
+// key differs in far fewer bits at the right key size.
+const XOR_THRESHOLD: f64 = 3.3;
+
+// Over n compared bytes, random data differs in 4 +- sqrt(2/n) bits per
+// byte. With at least 64 bytes, the threshold is four standard deviations
+// away, and random data passes with a chance of about 1 in 30000.
+const XOR_MIN_COMPARED: usize = 64;
+
+// How many of the best ranked key sizes to check.
+const XOR_NUM_TRIES: usize = 3;
+
+pub fn find_ecb_ciphertext<'a>(ciphertexts: &[&'a str]) -> Option<&'a str> {
+  ciphertexts.iter().find(|ct| is_ecb_ciphertext(ct.as_bytes(), 32)).cloned()
 }
 
 pub fn is_ecb_ciphertext(bytes: &[u8], blocksize: usize) -> bool {
@@ -24,16 +44,226 @@ pub fn is_ecb_ciphertext(bytes: &[u8], blocksize: usize) -> bool {
   false
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Structure {
+  Ecb,
+  // CBC, CTR or any other mode that hides repeated blocks.
+  BlockChained,
+  // XOR with a repeated key of the given size.
+  RepeatedXor(usize),
+  // A stream cipher, or anything else that isn't block-aligned.
+  Stream
+}
+
+#[derive(Debug)]
+pub struct Analysis {
+  pub blocksize: Option<usize>,
+  // Maps repeated blocks to all offsets they were found at.
+  pub repeated_blocks: HashMap<Vec<u8>, Vec<usize>>,
+  // Shannon entropy of every block, in bits per byte.
+  pub entropy: Vec<f64>,
+  // Whether the length is a multiple of the block size.
+  pub aligned: bool,
+  // Whether the data could be an IV followed by at least one block.
+  pub fits_iv: bool,
+  pub structure: Structure
+}
+
+pub fn analyze(bytes: &[u8]) -> Analysis {
+  let ecb_blocksize = determine_ecb_blocksize(bytes);
+
+  // Without repeated blocks, take the most common block size the length
+  // allows, 16 before 8. 32-byte blocks look like 16-byte ones then.
+  let blocksize = ecb_blocksize.or_else(|| {
+    BLOCKSIZES[..2].iter().find(|bs| bytes.len() > 0 && bytes.len() % **bs == 0).cloned()
+  });
+
+  let chunk_size = blocksize.unwrap_or(BLOCKSIZES[0]);
+  let entropy = Vec::from_iter(bytes.chunks(chunk_size).map(entropy));
+  let repeated_blocks = blocksize.map_or(HashMap::new(), |bs| find_repeated_blocks(bytes, bs));
+  let aligned = blocksize.is_some();
+  let fits_iv = blocksize.map_or(false, |bs| bytes.len() >= bs * 2);
+
+  let structure = if ecb_blocksize.is_some() {
+    Structure::Ecb
+  } else if let Some(key_size) = find_xor_key_size(bytes) {
+    Structure::RepeatedXor(key_size)
+  } else if aligned {
+    Structure::BlockChained
+  } else {
+    Structure::Stream
+  };
+
+  Analysis {
+    blocksize: blocksize,
+    repeated_blocks: repeated_blocks,
+    entropy: entropy,
+    aligned: aligned,
+    fits_iv: fits_iv,
+    structure: structure
+  }
+}
+
+// Analyze a file with one hex-encoded ciphertext per line.
+pub fn analyze_hex_lines(data: &str) -> Vec<Analysis> {
+  Vec::from_iter(data.split('\n').filter(|line| !line.is_empty()).map(|line| {
+    analyze(&line.from_hex())
+  }))
+}
+
+// Returns the block size that explains most repeated bytes. Repeated blocks
+// show up at half the block size too, so prefer larger block sizes.
+fn determine_ecb_blocksize(bytes: &[u8]) -> Option<usize> {
+  let mut best = None;
+  let mut best_repeated = 0;
+
+  for bs in BLOCKSIZES.iter().cloned() {
+    if bytes.len() % bs != 0 || !is_ecb_ciphertext(bytes, bs) {
+      continue;
+    }
+
+    let repeated = find_repeated_blocks(bytes, bs).values().fold(0, |sum, offsets| {
+      sum + (offsets.len() - 1) * bs
+    });
+
+    if repeated > best_repeated || (repeated == best_repeated && best.map_or(true, |best| bs > best)) {
+      best = Some(bs);
+      best_repeated = repeated;
+    }
+  }
+
+  best
+}
+
+pub fn find_repeated_blocks(bytes: &[u8], blocksize: usize) -> HashMap<Vec<u8>, Vec<usize>> {
+  let mut blocks: HashMap<Vec<u8>, Vec<usize>> = HashMap::new();
+
+  for (i, block) in bytes.chunks(blocksize).enumerate() {
+    blocks.entry(block.to_vec()).or_insert(vec!()).push(i * blocksize);
+  }
+
+  HashMap::from_iter(blocks.into_iter().filter(|&(_, ref offsets)| offsets.len() > 1))
+}
+
+pub fn entropy(bytes: &[u8]) -> f64 {
+  let mut counts = [0usize; 256];
+  for byte in bytes {
+    counts[*byte as usize] += 1;
+  }
+
+  let len = bytes.len() as f64;
+  counts.iter().filter(|count| **count > 0).fold(0.0, |sum, count| {
+    let p = *count as f64 / len;
+    sum - p * p.log2()
+  })
+}
+
+// The average number of differing bits per byte between adjacent chunks of
+// |key_size| bytes.
+pub fn normalized_hamming_distance(bytes: &[u8], key_size: usize) -> Option<f64> {
+  let chunks = Vec::from_iter(bytes.chunks(key_size).filter(|chunk| chunk.len() == key_size));
+
+  // We need enough compared bytes for this to mean anything.
+  if chunks.len() < 4 || (chunks.len() - 1) * key_size < XOR_MIN_COMPARED {
+    return None;
+  }
+
+  let dist = chunks.windows(2).fold(0, |dist, pair| {
+    dist + pair[0].hamming_distance(pair[1])
+  });
+
+  Some(dist as f64 / ((chunks.len() - 1) * key_size) as f64)
+}
+
+// Checks the key sizes challenge6 ranks best. Random data of any length is
+// unlikely to pass, as every check compares many bytes.
+fn find_xor_key_size(bytes: &[u8]) -> Option<usize> {
+  if bytes.len() < 3 * 40 {
+    return None;
+  }
+
+  let mut best = None;
+  let mut best_dist = XOR_THRESHOLD;
+
+  for key_size in rank_xor_keysizes(bytes).into_iter().take(XOR_NUM_TRIES) {
+    if let Some(dist) = normalized_hamming_distance(bytes, key_size) {
+      if dist < best_dist {
+        best = Some(key_size);
+        best_dist = dist;
+      }
+    }
+  }
+
+  best
+}
+
 #[cfg(test)]
 mod test {
+  use rand::{Rng, OsRng};
   use std::iter::FromIterator;
   use challenge4::read_file;
+  use challenge6::Base64Decoder;
+  use Structure;
+  use analyze;
+  use analyze_hex_lines;
   use find_ecb_ciphertext;
 
+  static ECB_CIPHERTEXT: &'static str = "d880619740a8a19b7840a8a31c810a3d08649af70dc06f4fd5d2d69c744cd283e2dd052f6b641dbf9d11b0348542bb5708649af70dc06f4fd5d2d69c744cd2839475c9dfdbc1d46597949d9c7e82bf5a08649af70dc06f4fd5d2d69c744cd28397a93eab8d6aecd566489154789a6b0308649af70dc06f4fd5d2d69c744cd283d403180c98c8f6db1f2a3f9c4040deb0ab51b29933f2c123c58386b06fba186a";
+
   #[test]
   fn test() {
     let data = read_file("data.txt");
     let ciphertexts = Vec::from_iter(data.split('\n'));
-    assert_eq!(find_ecb_ciphertext(&ciphertexts), "d880619740a8a19b7840a8a31c810a3d08649af70dc06f4fd5d2d69c744cd283e2dd052f6b641dbf9d11b0348542bb5708649af70dc06f4fd5d2d69c744cd2839475c9dfdbc1d46597949d9c7e82bf5a08649af70dc06f4fd5d2d69c744cd28397a93eab8d6aecd566489154789a6b0308649af70dc06f4fd5d2d69c744cd283d403180c98c8f6db1f2a3f9c4040deb0ab51b29933f2c123c58386b06fba186a");
+    assert_eq!(find_ecb_ciphertext(&ciphertexts), Some(ECB_CIPHERTEXT));
+    assert_eq!(find_ecb_ciphertext(&ciphertexts[..10]), None);
+  }
+
+  #[test]
+  fn test_analyze_hex_lines() {
+    let analyses = analyze_hex_lines(&read_file("data.txt"));
+    assert_eq!(analyses.len(), 204);
+
+    let ecb = Vec::from_iter(analyses.iter().filter(|a| a.structure == Structure::Ecb));
+    assert_eq!(ecb.len(), 1);
+    assert_eq!(ecb[0].blocksize, Some(16));
+
+    // One block, repeated four times.
+    assert_eq!(ecb[0].repeated_blocks.len(), 1);
+    assert_eq!(ecb[0].repeated_blocks.values().next().unwrap(), &vec!(16, 48, 80, 112));
+
+    // Everything else looks like a chained mode.
+    for analysis in analyses.iter().filter(|a| a.structure != Structure::Ecb) {
+      assert_eq!(analysis.structure, Structure::BlockChained);
+      assert!(analysis.aligned && analysis.fits_iv);
+      assert!(analysis.repeated_blocks.is_empty());
+      assert!(analysis.entropy.iter().all(|e| *e > 3.0));
+    }
+  }
+
+  #[test]
+  fn test_analyze_xor() {
+    let data = read_file("../challenge6/data.txt").replace("\n", "").from_base64();
+    let analysis = analyze(&data);
+    assert_eq!(analysis.structure, Structure::RepeatedXor(29));
+
+    let data = read_file("../challenge10/data.txt").replace("\n", "").from_base64();
+    assert_eq!(analyze(&data).structure, Structure::BlockChained);
+
+    // Cut off a few bytes to make it look like a stream cipher.
+    assert_eq!(analyze(&data[..data.len()-3]).structure, Structure::Stream);
+  }
+
+  #[test]
+  fn test_analyze_short() {
+    let mut rng = OsRng::new().unwrap();
+
+    // Short random data doesn't look like repeated XOR.
+    for len in (32..161).filter(|len| len % 16 == 0) {
+      for _ in 0..200 {
+        let mut data = vec!(0u8; len);
+        rng.fill_bytes(&mut data);
+        assert_eq!(analyze(&data).structure, Structure::BlockChained);
+      }
+    }
   }
 }
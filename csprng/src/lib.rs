@@ -0,0 +1,605 @@
+extern crate rand;
+extern crate openssl;
+extern crate challenge18;
+extern crate challenge21;
+
+#[cfg(test)]
+extern crate challenge1;
+
+use openssl::crypto::hash::{hash, Type};
+use rand::{Rng, SeedableRng};
+use std::cmp::min;
+use std::iter::{FromIterator, repeat};
+use challenge18::{AesCtr, Endian};
+use challenge21::linear_complexity;
+
+// SP 800-90A allows at most 2^19 bits per request, and 2^48 requests
+// between reseeds.
+const MAX_REQUEST: usize = 1 << 16;
+const RESEED_INTERVAL: u64 = 1 << 48;
+
+// CTR_DRBG with AES-128 and no derivation function, as in SP 800-90A.
+// Key and counter are both updated after every request.
+const CTR_SEED_LEN: usize = 32;
+
+// Hash_DRBG with SHA-256, as in SP 800-90A.
+const HASH_SEED_LEN: usize = 55;
+
+// Tests fail when their p-value drops below this.
+const ALPHA: f64 = 0.0001;
+
+// Splits |dest| into requests of at most MAX_REQUEST bytes.
+fn fill_in_requests<F>(dest: &mut [u8], mut generate: F) where F: FnMut(&mut [u8]) {
+  for chunk in dest.chunks_mut(MAX_REQUEST) {
+    generate(chunk);
+  }
+}
+
+// Four bytes of the byte stream, little-endian.
+fn next_u32_from_bytes<R: Rng>(rng: &mut R) -> u32 {
+  let mut bytes = [0u8; 4];
+  rng.fill_bytes(&mut bytes);
+  bytes.iter().rev().fold(0, |word, byte| (word << 8) | *byte as u32)
+}
+
+pub struct CtrDrbg {
+  key: Vec<u8>,
+  v: Vec<u8>,
+  reseed_counter: u64
+}
+
+impl CtrDrbg {
+  // |entropy| must be 32 bytes, |personalization| at most 32 bytes.
+  pub fn new(entropy: &[u8], personalization: &[u8]) -> CtrDrbg {
+    assert!(entropy.len() == CTR_SEED_LEN && personalization.len() <= CTR_SEED_LEN);
+
+    let mut drbg = CtrDrbg { key: vec!(0; 16), v: vec!(0; 16), reseed_counter: 1 };
+    let seed = Vec::from_iter(entropy.iter().zip(pad_seed(personalization)).map(|(a, b)| a ^ b));
+    drbg.update(&seed);
+    drbg
+  }
+
+  pub fn reseed_with(&mut self, entropy: &[u8], additional: &[u8]) {
+    assert!(entropy.len() == CTR_SEED_LEN && additional.len() <= CTR_SEED_LEN);
+
+    let seed = Vec::from_iter(entropy.iter().zip(pad_seed(additional)).map(|(a, b)| a ^ b));
+    self.update(&seed);
+    self.reseed_counter = 1;
+  }
+
+  // Fills |dest| with a single request of at most 2^16 bytes.
+  pub fn generate(&mut self, dest: &mut [u8], additional: &[u8]) {
+    assert!(dest.len() <= MAX_REQUEST && additional.len() <= CTR_SEED_LEN);
+    assert!(self.reseed_counter <= RESEED_INTERVAL, "reseed required");
+
+    // Only absent additional input skips the update, not an all-zero one.
+    let present = !additional.is_empty();
+    let additional = pad_seed(additional);
+    if present {
+      self.update(&additional);
+    }
+
+    // The counter blocks V+1, V+2, ... are exactly AES-CTR's keystream.
+    let mut ctr = self.keystream();
+    let output = ctr.update(&vec!(0; dest.len()));
+    for (dst, src) in dest.iter_mut().zip(output) {
+      *dst = src;
+    }
+
+    // Update with the counter blocks after the last one we used.
+    let blocks = (dest.len() + 15) / 16;
+    ctr.seek(16 * (blocks as u64 + 1));
+    self.update_with(ctr, &additional);
+    self.reseed_counter += 1;
+  }
+
+  // The keystream starting at V+1. The counter is the whole block.
+  fn keystream(&self) -> AesCtr {
+    let mut ctr = AesCtr::new(&self.key, &self.v, 16, Endian::Big);
+    ctr.seek(16);
+    ctr
+  }
+
+  fn update(&mut self, provided: &[u8]) {
+    let ctr = self.keystream();
+    self.update_with(ctr, provided);
+  }
+
+  // Takes the next two keystream blocks, xored with |provided|, as the new
+  // key and V.
+  fn update_with(&mut self, mut ctr: AesCtr, provided: &[u8]) {
+    let temp = ctr.update(provided);
+    self.key = temp[..16].to_vec();
+    self.v = temp[16..].to_vec();
+  }
+}
+
+// Pads |data| with zeros to the seed length.
+fn pad_seed(data: &[u8]) -> Vec<u8> {
+  let mut padded = data.to_vec();
+  padded.extend(repeat(0).take(CTR_SEED_LEN - data.len()));
+  padded
+}
+
+impl<'a> SeedableRng<&'a [u8]> for CtrDrbg {
+  fn reseed(&mut self, entropy: &'a [u8]) {
+    self.reseed_with(entropy, &[]);
+  }
+
+  fn from_seed(entropy: &'a [u8]) -> CtrDrbg {
+    CtrDrbg::new(entropy, &[])
+  }
+}
+
+impl Rng for CtrDrbg {
+  fn next_u32(&mut self) -> u32 {
+    next_u32_from_bytes(self)
+  }
+
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    fill_in_requests(dest, |chunk| self.generate(chunk, &[]));
+  }
+}
+
+pub struct HashDrbg {
+  v: Vec<u8>,
+  c: Vec<u8>,
+  reseed_counter: u64
+}
+
+impl HashDrbg {
+  pub fn new(entropy: &[u8], nonce: &[u8], personalization: &[u8]) -> HashDrbg {
+    let mut seed = entropy.to_vec();
+    seed.extend(nonce.iter().cloned());
+    seed.extend(personalization.iter().cloned());
+
+    let v = hash_df(&seed, HASH_SEED_LEN);
+    let c = hash_df(&prefixed(0, &[&v]), HASH_SEED_LEN);
+    HashDrbg { v: v, c: c, reseed_counter: 1 }
+  }
+
+  pub fn reseed_with(&mut self, entropy: &[u8], additional: &[u8]) {
+    self.v = hash_df(&prefixed(1, &[&self.v, entropy, additional]), HASH_SEED_LEN);
+    self.c = hash_df(&prefixed(0, &[&self.v]), HASH_SEED_LEN);
+    self.reseed_counter = 1;
+  }
+
+  // Fills |dest| with a single request of at most 2^16 bytes.
+  pub fn generate(&mut self, dest: &mut [u8], additional: &[u8]) {
+    assert!(dest.len() <= MAX_REQUEST);
+    assert!(self.reseed_counter <= RESEED_INTERVAL, "reseed required");
+
+    if !additional.is_empty() {
+      let w = hash(Type::SHA256, &prefixed(2, &[&self.v, additional]));
+      add_be(&mut self.v, &w);
+    }
+
+    // Hash V, V+1, V+2, ...
+    let mut data = self.v.clone();
+    for chunk in dest.chunks_mut(32) {
+      let output = hash(Type::SHA256, &data);
+      for (dst, src) in chunk.iter_mut().zip(output) {
+        *dst = src;
+      }
+      add_be(&mut data, &[1]);
+    }
+
+    // V = V + H(0x03 || V) + C + reseed_counter
+    let h = hash(Type::SHA256, &prefixed(3, &[&self.v]));
+    let c = self.c.clone();
+    let counter = Vec::from_iter((0..8).rev().map(|i| (self.reseed_counter >> (i * 8)) as u8));
+    add_be(&mut self.v, &h);
+    add_be(&mut self.v, &c);
+    add_be(&mut self.v, &counter);
+    self.reseed_counter += 1;
+  }
+}
+
+// The concatenation of |prefix| and |parts|.
+fn prefixed(prefix: u8, parts: &[&[u8]]) -> Vec<u8> {
+  let mut data = vec!(prefix);
+  for part in parts {
+    data.extend(part.iter().cloned());
+  }
+  data
+}
+
+// Hash_df from SP 800-90A, derives |len| bytes from |input|.
+fn hash_df(input: &[u8], len: usize) -> Vec<u8> {
+  let bits = len * 8;
+  let mut output = Vec::with_capacity(len + 32);
+
+  for counter in 1..(len + 31) / 32 + 1 {
+    let mut data = vec!(counter as u8, (bits >> 24) as u8, (bits >> 16) as u8, (bits >> 8) as u8, bits as u8);
+    data.extend(input.iter().cloned());
+    output.extend(hash(Type::SHA256, &data));
+  }
+
+  output.truncate(len);
+  output
+}
+
+// Adds the big-endian number |b| to |a|, modulo 2^(8 * |a.len()|).
+fn add_be(a: &mut [u8], b: &[u8]) {
+  let mut carry = 0u16;
+
+  for i in 0..a.len() {
+    let pos = a.len() - 1 - i;
+    let other = if i < b.len() { b[b.len() - 1 - i] as u16 } else { 0 };
+
+    let sum = a[pos] as u16 + other + carry;
+    a[pos] = sum as u8;
+    carry = sum >> 8;
+  }
+}
+
+impl<'a> SeedableRng<&'a [u8]> for HashDrbg {
+  fn reseed(&mut self, entropy: &'a [u8]) {
+    self.reseed_with(entropy, &[]);
+  }
+
+  fn from_seed(entropy: &'a [u8]) -> HashDrbg {
+    HashDrbg::new(entropy, &[], &[])
+  }
+}
+
+impl Rng for HashDrbg {
+  fn next_u32(&mut self) -> u32 {
+    next_u32_from_bytes(self)
+  }
+
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    fill_in_requests(dest, |chunk| self.generate(chunk, &[]));
+  }
+}
+
+// The original ChaCha20 with a 64-bit block counter and a 64-bit nonce.
+pub struct ChaCha20Rng {
+  state: [u32; 16],
+  block: [u8; 64],
+  // The next unused byte of |block|, 64 if there is none.
+  index: usize
+}
+
+impl ChaCha20Rng {
+  pub fn with_nonce(key: &[u8], nonce: u64) -> ChaCha20Rng {
+    let mut rng = ChaCha20Rng { state: [0; 16], block: [0; 64], index: 64 };
+    rng.init(key, nonce);
+    rng
+  }
+
+  fn init(&mut self, key: &[u8], nonce: u64) {
+    assert!(key.len() == 32);
+
+    // "expand 32-byte k"
+    self.state[0] = 0x61707865;
+    self.state[1] = 0x3320646e;
+    self.state[2] = 0x79622d32;
+    self.state[3] = 0x6b206574;
+
+    for (i, chunk) in key.chunks(4).enumerate() {
+      self.state[4 + i] = chunk.iter().rev().fold(0, |word, byte| (word << 8) | *byte as u32);
+    }
+
+    self.state[12] = 0;
+    self.state[13] = 0;
+    self.state[14] = nonce as u32;
+    self.state[15] = (nonce >> 32) as u32;
+    self.index = 64;
+  }
+
+  // Computes the next block and increments the counter.
+  fn next_block(&mut self) {
+    let mut x = self.state;
+
+    for _ in 0..10 {
+      quarter_round(&mut x, 0, 4, 8, 12);
+      quarter_round(&mut x, 1, 5, 9, 13);
+      quarter_round(&mut x, 2, 6, 10, 14);
+      quarter_round(&mut x, 3, 7, 11, 15);
+      quarter_round(&mut x, 0, 5, 10, 15);
+      quarter_round(&mut x, 1, 6, 11, 12);
+      quarter_round(&mut x, 2, 7, 8, 13);
+      quarter_round(&mut x, 3, 4, 9, 14);
+    }
+
+    for i in 0..16 {
+      let word = x[i].wrapping_add(self.state[i]);
+      for j in 0..4 {
+        self.block[i * 4 + j] = (word >> (j * 8)) as u8;
+      }
+    }
+
+    self.state[12] = self.state[12].wrapping_add(1);
+    if self.state[12] == 0 {
+      self.state[13] = self.state[13].wrapping_add(1);
+    }
+
+    self.index = 0;
+  }
+}
+
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+  x[a] = x[a].wrapping_add(x[b]); x[d] = (x[d] ^ x[a]).rotate_left(16);
+  x[c] = x[c].wrapping_add(x[d]); x[b] = (x[b] ^ x[c]).rotate_left(12);
+  x[a] = x[a].wrapping_add(x[b]); x[d] = (x[d] ^ x[a]).rotate_left(8);
+  x[c] = x[c].wrapping_add(x[d]); x[b] = (x[b] ^ x[c]).rotate_left(7);
+}
+
+impl<'a> SeedableRng<&'a [u8]> for ChaCha20Rng {
+  fn reseed(&mut self, key: &'a [u8]) {
+    self.init(key, 0);
+  }
+
+  fn from_seed(key: &'a [u8]) -> ChaCha20Rng {
+    ChaCha20Rng::with_nonce(key, 0)
+  }
+}
+
+impl Rng for ChaCha20Rng {
+  fn next_u32(&mut self) -> u32 {
+    next_u32_from_bytes(self)
+  }
+
+  // The keystream, byte by byte.
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    let mut pos = 0;
+
+    while pos < dest.len() {
+      if self.index == 64 {
+        self.next_block();
+      }
+
+      let len = min(64 - self.index, dest.len() - pos);
+      for i in 0..len {
+        dest[pos + i] = self.block[self.index + i];
+      }
+
+      self.index += len;
+      pos += len;
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct TestResult {
+  pub name: &'static str,
+  pub p_value: f64
+}
+
+impl TestResult {
+  pub fn passed(&self) -> bool {
+    self.p_value >= ALPHA
+  }
+}
+
+// The bits of |bytes|, least significant bit first.
+fn bits(bytes: &[u8]) -> Vec<bool> {
+  Vec::from_iter(bytes.iter().flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1)))
+}
+
+// The complementary error function, with a fractional error below 1.2e-7.
+// See Numerical Recipes, section 6.2.
+fn erfc(x: f64) -> f64 {
+  let z = x.abs();
+  let t = 1.0 / (1.0 + 0.5 * z);
+  let poly = -1.26551223 + t * (1.00002368 + t * (0.37409196 + t * (0.09678418 +
+    t * (-0.18628806 + t * (0.27886807 + t * (-1.13520398 + t * (1.48851587 +
+    t * (-0.82215223 + t * 0.17087277))))))));
+  let r = t * (-z * z + poly).exp();
+  if x >= 0.0 { r } else { 2.0 - r }
+}
+
+// The two-sided p-value of a standard normal |z|.
+fn normal_p_value(z: f64) -> f64 {
+  erfc(z.abs() / 2f64.sqrt())
+}
+
+// NIST SP 800-22 monobit test: are there as many ones as zeros?
+pub fn frequency(bits: &[bool]) -> TestResult {
+  let sum = bits.iter().fold(0i64, |sum, bit| if *bit { sum + 1 } else { sum - 1 });
+  let z = sum as f64 / (bits.len() as f64).sqrt();
+  TestResult { name: "frequency", p_value: normal_p_value(z) }
+}
+
+// NIST SP 800-22 runs test: do bits change as often as they should?
+pub fn runs(bits: &[bool]) -> TestResult {
+  let n = bits.len() as f64;
+  let ones = bits.iter().filter(|bit| **bit).count() as f64;
+  let pi = ones / n;
+
+  // The test only makes sense if the frequency is about right.
+  if (pi - 0.5).abs() >= 2.0 / n.sqrt() {
+    return TestResult { name: "runs", p_value: 0.0 };
+  }
+
+  let runs = 1 + (1..bits.len()).filter(|i| bits[*i] != bits[*i - 1]).count();
+  let expected = 2.0 * n * pi * (1.0 - pi);
+  let z = (runs as f64 - expected) / (2.0 * n.sqrt() * pi * (1.0 - pi));
+  TestResult { name: "runs", p_value: normal_p_value(z) }
+}
+
+// Knuth's serial correlation coefficient of consecutive bytes, wrapping
+// around at the end. It's about normal with mean -1/(n-1) and variance 1/n.
+pub fn serial_correlation(bytes: &[u8]) -> TestResult {
+  let n = bytes.len() as f64;
+  let (mut sum, mut squares, mut products) = (0f64, 0f64, 0f64);
+
+  for i in 0..bytes.len() {
+    let (u, next) = (bytes[i] as f64, bytes[(i + 1) % bytes.len()] as f64);
+    sum += u;
+    squares += u * u;
+    products += u * next;
+  }
+
+  let c = (n * products - sum * sum) / (n * squares - sum * sum);
+  let z = (c + 1.0 / (n - 1.0)) * n.sqrt();
+  TestResult { name: "serial correlation", p_value: normal_p_value(z) }
+}
+
+// Marsaglia's birthday spacings test. Every group of 512 birthdays in a year
+// of 2^24 days should have about Poisson(2) duplicate spacings. We sum them
+// over all groups and compare with the normal approximation.
+pub fn birthday_spacings(words: &[u32]) -> TestResult {
+  let (birthdays, days) = (512, 1u64 << 24);
+  let lambda = (birthdays as f64).powi(3) / (4.0 * days as f64);
+
+  let mut duplicates = 0;
+  let groups = words.len() / birthdays;
+
+  for group in words.chunks(birthdays).take(groups) {
+    let mut days = Vec::from_iter(group.iter().map(|word| word >> 8));
+    days.sort();
+
+    let mut spacings = Vec::from_iter((1..days.len()).map(|i| days[i] - days[i - 1]));
+    spacings.sort();
+
+    duplicates += (1..spacings.len()).filter(|i| spacings[*i] == spacings[*i - 1]).count();
+  }
+
+  let expected = lambda * groups as f64;
+  let z = (duplicates as f64 - expected) / expected.sqrt();
+  TestResult { name: "birthday spacings", p_value: normal_p_value(z) }
+}
+
+// The linear complexity of a random sequence of n bits is about n/2, and
+// deviating by k is roughly 4^-k likely. A generator with a linear
+// recurrence, like MT19937, falls way short.
+pub fn linear_complexity_test(bits: &[bool]) -> TestResult {
+  let deviation = (linear_complexity(bits) as f64 - bits.len() as f64 / 2.0).abs();
+  let p_value = 2f64.powf(2.0 - 2.0 * deviation).min(1.0);
+  TestResult { name: "linear complexity", p_value: p_value }
+}
+
+// Runs all tests on output of |rng|. The linear complexity test looks at
+// the lowest bit of 50000 consecutive 32-bit words.
+pub fn battery<R: Rng>(rng: &mut R) -> Vec<TestResult> {
+  let mut bytes = vec!(0u8; 1 << 17);
+  rng.fill_bytes(&mut bytes);
+  let stream = bits(&bytes);
+
+  let words = Vec::from_iter((0..512 * 200).map(|_| rng.next_u32()));
+  let lowest = Vec::from_iter((0..50000).map(|_| rng.next_u32() & 1 == 1));
+
+  vec!(
+    frequency(&stream),
+    runs(&stream),
+    serial_correlation(&bytes),
+    birthday_spacings(&words),
+    linear_complexity_test(&lowest)
+  )
+}
+
+#[cfg(test)]
+mod test {
+  use challenge1::HexDecoder;
+  use challenge21::{MT19937RNG, linear_complexity};
+  use rand::{Rng, SeedableRng};
+  use std::iter::FromIterator;
+  use ChaCha20Rng;
+  use CtrDrbg;
+  use HashDrbg;
+  use TestResult;
+  use battery;
+  use frequency;
+  use runs;
+
+  #[test]
+  fn test_ctr_drbg() {
+    // Self-generated regression vectors, not from NIST CAVP. They pin down
+    // derivation, reseeding and additional input.
+    let entropy = Vec::from_iter(0u8..32);
+    let mut drbg = CtrDrbg::from_seed(&entropy[..]);
+
+    let mut output = [0u8; 20];
+    drbg.generate(&mut output, &[]);
+    assert_eq!(output.to_vec(), "1686ffcf9f358be74452e647ba156aab05135797".from_hex());
+
+    let mut output = [0u8; 37];
+    drbg.generate(&mut output, b"extra");
+    assert_eq!(output.to_vec(), "ae5ccd25d81e4a78d3a411b8083ad745eaa0445d7806d91aebd86b19ca2eae6f8ade0b2330".from_hex());
+
+    let entropy = Vec::from_iter(32u8..64);
+    drbg.reseed(&entropy[..]);
+
+    let mut output = [0u8; 16];
+    drbg.fill_bytes(&mut output);
+    assert_eq!(output.to_vec(), "a16fee1020bc7d7723dc7bf05054cf69".from_hex());
+
+    // Zeros are additional input too, only an empty one is absent.
+    let (mut absent, mut zeros) = ([0u8; 16], [0u8; 16]);
+    CtrDrbg::from_seed(&entropy[..]).generate(&mut absent, &[]);
+    CtrDrbg::from_seed(&entropy[..]).generate(&mut zeros, &[0; 16]);
+    assert!(absent != zeros);
+  }
+
+  #[test]
+  fn test_hash_drbg() {
+    // NIST CAVP, Hash_DRBG SHA-256, no prediction resistance, count 0.
+    let entropy = "a65ad0f345db4e0effe875c3a2e71f42c7129d620ff5c119a9ef55f05185e0fb".from_hex();
+    let nonce = "8581f9317517276e06e9607ddbcbcc2e".from_hex();
+    let mut drbg = HashDrbg::new(&entropy, &nonce, &[]);
+
+    let mut output = [0u8; 128];
+    drbg.generate(&mut output, &[]);
+    drbg.generate(&mut output, &[]);
+    assert_eq!(output.to_vec(), "d3e160c35b99f340b2628264d1751060e0045da383ff57a57d73a673d2b8d80daaf6a6c35a91bb4579d73fd0c8fed111b0391306828adfed528f018121b3febdc343e797b87dbb63db1333ded9d1ece177cfa6b71fe8ab1da46624ed6415e51ccde2c7ca86e283990eeaeb91120415528b2295910281b02dd431f4c9f70427df".from_hex());
+  }
+
+  #[test]
+  fn test_chacha20() {
+    // RFC 7539, appendix A.1, test vectors #1 and #2.
+    let mut rng = ChaCha20Rng::from_seed(&[0u8; 32][..]);
+    let mut output = [0u8; 128];
+    rng.fill_bytes(&mut output);
+    assert_eq!(output.to_vec(), "76b8e0ada0f13d90405d6ae55386bd28bdd219b8a08ded1aa836efcc8b770dc7da41597c5157488d7724e03fb8d84a376a43b8f41518a11cc387b669b2ee65869f07e7be5551387a98ba977c732d080dcb0f29a048e3656912c6533e32ee7aed29b721769ce64e43d57133b074d839d531ed1f28510afb45ace10a1f4b794d6f".from_hex());
+
+    // Words are little-endian.
+    let mut rng = ChaCha20Rng::from_seed(&[0u8; 32][..]);
+    assert_eq!(rng.next_u32(), 0xade0b876);
+
+    let key = Vec::from_iter(0u8..32);
+    let mut rng = ChaCha20Rng::with_nonce(&key, 0x0123456789abcdef);
+    let mut output = [0u8; 64];
+    for chunk in output.chunks_mut(7) {
+      rng.fill_bytes(chunk);
+    }
+    assert_eq!(output.to_vec(), "2ef441c1f0220993293056c89fc59053bc3b2743e435c49ce1ef9ecd8170a35044d6664395d5a01f84b82f1f0c87701186efd87cfe32136698e81567b54a6e85".from_hex());
+  }
+
+  #[test]
+  fn test_statistics() {
+    // Obviously broken sequences.
+    let zeros = vec!(false; 1000);
+    assert!(!frequency(&zeros).passed());
+
+    let alternating = Vec::from_iter((0..1000).map(|i| i % 2 == 0));
+    assert!(frequency(&alternating).passed());
+    assert!(!runs(&alternating).passed());
+
+    // An LFSR with x^5 + x^2 + 1.
+    let mut lfsr = vec!(true, false, false, true, true);
+    for i in 5..200 {
+      let bit = lfsr[i - 5] ^ lfsr[i - 3];
+      lfsr.push(bit);
+    }
+    assert_eq!(linear_complexity(&lfsr), 5);
+  }
+
+  fn failed(results: &[TestResult]) -> Vec<&'static str> {
+    Vec::from_iter(results.iter().filter(|result| !result.passed()).map(|result| result.name))
+  }
+
+  #[test]
+  fn test_battery() {
+    let seed = Vec::from_iter(0u8..32);
+
+    // MT19937 looks random, except for its linearity.
+    let results = battery(&mut MT19937RNG::from_seed(5489));
+    assert_eq!(failed(&results), vec!("linear complexity"));
+
+    assert!(failed(&battery(&mut CtrDrbg::from_seed(&seed[..]))).is_empty());
+    assert!(failed(&battery(&mut HashDrbg::from_seed(&seed[..]))).is_empty());
+    assert!(failed(&battery(&mut ChaCha20Rng::from_seed(&seed[..]))).is_empty());
+  }
+}